@@ -1,11 +1,29 @@
-use std::{cell::RefCell, rc::Rc};
+use std::collections::HashSet;
 
-use crate::generate::generate;
-use crate::point::{interpolate, Point};
+use crate::compare::{command_lines, line_changes, waypoint_changes, LineChange, WaypointChange};
+use crate::field::{Field, FieldImage, Frame, Landmark, Obstacle, Origin};
+use crate::file::save_file;
+use crate::generate::{
+    commands, format_commands, generate_path, path_waypoints, segment_commands, waypoints,
+    ArcStyle, Backend, BranchStyle, Command, DriveUnit, GenerateOptions, Templates,
+};
+use crate::geometry::{segments, Segment};
+use crate::images::ImageStore;
+use crate::keymap::{Action, Keymap};
+use crate::lemlib;
+use crate::parse::parse;
+use crate::path::{Branch, Path};
+use crate::point::Point;
+use crate::project::Project;
+use crate::pursuit::{lookahead, sample};
+use crate::render::{render_png, render_svg, Scene};
+use crate::report::{report, ReportFormat};
+use crate::robot::{Drivetrain, Robot};
+use crate::telemetry::{deviations, end_error, parse_log, Run};
 use egui::{pos2, Color32, FontDefinitions, FontFamily, Pos2, Stroke, TextEdit, Vec2};
 #[allow(deprecated)]
 use egui_extras::RetainedImage;
-use std::sync::Arc;
+use uuid::Uuid;
 
 /*
 // Uncomment this section to get access to the console_log macro
@@ -44,14 +62,27 @@ pub enum CursorMode {
     Insert,
     Delete,
     Trim,
+    Measure,
+    /// Clicking the field image's landmarks
+    Calibrate,
 }
 
-/// Represents chosen background image.
-#[derive(serde::Deserialize, serde::Serialize, Debug, PartialEq, Eq)]
-pub enum Background {
-    Game,
-    Skills,
-    Custom,
+/// Older versions of the path are kept at most.
+const HISTORY_LENGTH: usize = 20;
+
+/// Changes to the path that can be undone at most.
+const UNDO_LENGTH: usize = 100;
+
+/// Inches an arrow key moves the inspected point, alone, with Alt and with Shift.
+const NUDGE: f32 = 1.0;
+const NUDGE_FINE: f32 = 0.1;
+const NUDGE_COARSE: f32 = 6.0;
+
+/// A saved copy of the path and its settings, to compare against.
+#[derive(serde::Deserialize, serde::Serialize, Clone)]
+pub struct Version {
+    pub label: String,
+    pub project: Project,
 }
 
 /// We derive Deserialize/Serialize so we can persist app state on shutdown.
@@ -59,8 +90,6 @@ pub enum Background {
 #[serde(default)] // if we add new fields, give them default values when deserializing old state
 #[allow(deprecated)]
 pub struct PathyApp {
-    /// Physical size
-    pub size: f32,
     /// Screen scale
     pub scale: u32,
     /// Current cursor mode
@@ -69,40 +98,137 @@ pub struct PathyApp {
     /// Current background image
     #[serde(skip)]
     pub overlay: Option<RetainedImage>,
-    /// Uploaded background image data
-    pub uploaded: Option<Arc<[u8]>>,
-    /// Field background state
-    pub background: Background,
+    /// Uploaded field images, saved apart from the app's state
+    #[serde(skip)]
+    pub images: ImageStore,
+    /// Field the path is drawn on
+    pub field: Field,
+    /// Saved custom field definitions
+    pub fields: Vec<Field>,
     /// Bezier points
     #[serde(skip)]
-    pub points: Vec<Rc<RefCell<Point>>>,
-    /// Locked selected point
+    pub path: Path,
+    /// Locked selected point, held until the mouse is released
     #[serde(skip)]
-    pub selected: Option<Rc<RefCell<Point>>>,
+    pub selected: Option<Uuid>,
     /// Inspected point
     #[serde(skip)]
-    pub inspecting: Option<Rc<RefCell<Point>>>,
+    pub inspecting: Option<Uuid>,
+    /// Branch that created points are added to, instead of the main path
+    #[serde(skip)]
+    pub branching: Option<usize>,
+    /// Points whose line has finished animating in
+    #[serde(skip)]
+    pub animated: HashSet<Uuid>,
     /// Generated code
     pub generated: String,
+    /// Why edited code couldn't be read back into a path
+    #[serde(skip)]
+    pub code_error: Option<String>,
+    /// Code generation settings
+    pub options: GenerateOptions,
+    /// Robot driving the path
+    pub robot: Robot,
+    /// Saved robot profiles
+    pub robots: Vec<Robot>,
+    /// Distance travelled along the path during playback
+    #[serde(skip)]
+    pub playback: Option<f32>,
+    /// Whether playback is running
+    #[serde(skip)]
+    pub playing: bool,
+    /// Logged runs shown over the path
+    #[serde(skip)]
+    pub runs: Vec<Run>,
+    /// Saved versions of the path, oldest first
+    pub history: Vec<Version>,
+    /// Number of versions saved so far, for labelling the next one
+    pub versions_saved: u32,
+    /// Version shown as a ghost under the path
+    #[serde(skip)]
+    pub compare: Option<Version>,
+    /// Shortcuts bound to each action
+    pub keymap: Keymap,
+    /// Action waiting for a new shortcut to be pressed
+    #[serde(skip)]
+    pub rebinding: Option<Action>,
+    /// Search text and highlighted entry of the open command palette
+    #[serde(skip)]
+    pub palette: Option<(String, usize)>,
+    /// Earlier versions of the path, most recent last
+    #[serde(skip)]
+    pub undo: Vec<Path>,
+    /// Undone versions of the path, most recent last
+    #[serde(skip)]
+    pub redo: Vec<Path>,
+    /// The path as of the last undo step, to notice when it changes
+    #[serde(skip)]
+    pub undo_base: Option<Path>,
+    /// Start of the measurement and, once clicked, its end, in field inches
+    #[serde(skip)]
+    pub measure: Option<(Pos2, Option<Pos2>)>,
+    /// Landmarks being clicked on the field image, and how many have been clicked
+    #[serde(skip)]
+    pub calibrating: Option<(Vec<Landmark>, usize)>,
+    /// Where the field was drawn last frame, for reading the cursor outside the field panel
+    #[serde(skip)]
+    pub field_rect: egui::Rect,
+    /// Name of the auton
+    pub name: String,
     /// Generated save data
     pub save_data: String,
+    /// Result of the last import or export, shown under the save data
+    #[serde(skip)]
+    pub message: Option<String>,
+    /// Width of exported pictures, in pixels
+    pub export_size: u32,
+    /// Whether exported pictures show the robot at every waypoint
+    pub export_footprints: bool,
+    /// Document format of exported reports
+    pub report_format: ReportFormat,
 }
 
 impl Default for PathyApp {
     fn default() -> Self {
         Self {
             // Example stuff:
-            size: 140.5,
             scale: 720,
             cursor_mode: CursorMode::Default,
             overlay: None,
-            uploaded: None,
-            background: Background::Game,
-            points: Vec::new(),
+            images: ImageStore::default(),
+            field: Field::default(),
+            fields: Vec::new(),
+            path: Path::default(),
             selected: None,
             inspecting: None,
+            branching: None,
+            animated: HashSet::new(),
             generated: String::new(),
+            code_error: None,
+            options: GenerateOptions::default(),
+            robot: Robot::default(),
+            robots: vec![Robot::default()],
+            playback: None,
+            playing: false,
+            runs: Vec::new(),
+            history: Vec::new(),
+            versions_saved: 0,
+            compare: None,
+            keymap: Keymap::default(),
+            rebinding: None,
+            palette: None,
+            undo: Vec::new(),
+            redo: Vec::new(),
+            undo_base: None,
+            measure: None,
+            calibrating: None,
+            field_rect: egui::Rect::NOTHING,
+            name: String::new(),
             save_data: String::new(),
+            message: None,
+            export_size: 1440,
+            export_footprints: true,
+            report_format: ReportFormat::Markdown,
         }
     }
 }
@@ -141,7 +267,17 @@ impl PathyApp {
 
         // load saved path
         if let Some(storage) = cc.storage {
-            app.load_save(&eframe::get_value::<Vec<Point>>(storage, "path").unwrap_or_default());
+            app.path = eframe::get_value(storage, "path").unwrap_or_default();
+        }
+
+        // Images used to be saved inside the fields, so move them into the store
+        app.images = ImageStore::open(&cc.egui_ctx);
+        for field in std::iter::once(&mut app.field).chain(&mut app.fields) {
+            if let FieldImage::Custom(bytes) = &field.image {
+                if let Ok(name) = app.images.add(&field.name, bytes) {
+                    field.image = FieldImage::Stored(name);
+                }
+            }
         }
 
         // Generate code and load overlay on startup
@@ -151,45 +287,1281 @@ impl PathyApp {
     }
     /// Update generated code
     fn generate(&mut self) {
-        self.generated = generate(&self.points);
+        self.generated = generate_path(&self.path, &self.robot, &self.options);
+        self.code_error = None;
+    }
+    /// Screen pixels per field inch
+    fn ratio(&self) -> f32 {
+        self.scale as f32 / self.field.width
     }
     /// Update field image
     #[allow(deprecated)]
     fn load_field_overlay(&mut self) {
-        match self.background {
-            Background::Game => {
-                self.overlay = Some(
-                    RetainedImage::from_image_bytes("", include_bytes!("../assets/pushback.png"))
-                        .unwrap(),
+        self.overlay = self
+            .images
+            .resolve(&self.field)
+            .image
+            .bytes()
+            .and_then(|bytes| RetainedImage::from_image_bytes("", bytes).ok());
+    }
+    /// Stores an uploaded image and puts it on the field
+    fn add_image(&mut self, name: &str, bytes: &[u8]) {
+        match self.images.add(name, bytes) {
+            Ok(name) => {
+                self.field.image = FieldImage::Stored(name);
+                // Landmarks clicked on another picture won't line this one up
+                self.field.calibration.clear();
+                self.load_field_overlay();
+            }
+            Err(e) => self.message = Some(e),
+        }
+    }
+    /// Switches to a field, keeping its coordinates for generated code
+    fn set_field(&mut self, field: Field) {
+        self.field = field;
+        self.calibrating = None;
+        self.options.frame = self.field.frame();
+        self.load_field_overlay();
+        self.generate();
+    }
+    /// Saves a picture of the field with the path drawn on it
+    fn export_picture(&mut self, svg: bool) {
+        let field = self.images.resolve(&self.field);
+        let scene = Scene {
+            path: &self.path,
+            robot: &self.robot,
+            field: &field,
+            footprints: self.export_footprints,
+        };
+        let result = if svg {
+            save_file("path.svg", render_svg(&scene, self.export_size).as_bytes())
+        } else {
+            render_png(&scene, self.export_size).and_then(|png| save_file("path.png", &png))
+        };
+        self.message = Some(result.unwrap_or_else(|err| err));
+    }
+    /// Saves a notebook report of the path, with a picture of the field
+    fn export_report(&mut self) {
+        let field = self.images.resolve(&self.field);
+        let scene = Scene {
+            path: &self.path,
+            robot: &self.robot,
+            field: &field,
+            footprints: self.export_footprints,
+        };
+        let image = render_png(&scene, self.export_size);
+        let text = report(
+            &self.path,
+            &self.robot,
+            &self.options,
+            image.as_deref().ok(),
+            self.report_format,
+        );
+        let name = format!("report.{}", self.report_format.extension());
+        self.message = Some(save_file(&name, text.as_bytes()).unwrap_or_else(|err| err));
+    }
+    /// Replaces the path with one read from a LemLib path file
+    fn import_lemlib(&mut self, text: &str) {
+        match lemlib::import(text, self.field.width) {
+            Ok(path) => {
+                self.path = path;
+                self.message = None;
+                self.generate();
+            }
+            Err(err) => self.message = Some(err),
+        }
+    }
+    /// The path with everything needed to generate code from it
+    fn project(&self) -> Project {
+        Project {
+            name: self.name.clone(),
+            path: self.path.clone(),
+            robot: self.robot.clone(),
+            options: self.options.clone(),
+        }
+    }
+    /// Reads an odometry log, measured like generated code, into a run
+    fn import_log(&mut self, name: &str, text: &str) {
+        match parse_log(text, &self.options.frame) {
+            Ok(poses) => {
+                self.runs.push(Run {
+                    name: name.to_string(),
+                    poses,
+                });
+                self.message = None;
+            }
+            Err(err) => self.message = Some(err),
+        }
+    }
+    /// Draws the code generation settings, returning true if any changed
+    fn options_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut updated = false;
+        ui.horizontal(|ui| {
+            ui.label("Output: ");
+            egui::ComboBox::from_id_salt("backend")
+                .selected_text(format!("{:?}", self.options.backend))
+                .show_ui(ui, |ui| {
+                    for backend in [
+                        Backend::Chassis,
+                        Backend::PursuitArray,
+                        Backend::PursuitFile,
+                        Backend::TrajectoryCsv,
+                        Backend::TrajectoryHeader,
+                    ] {
+                        updated |= ui
+                            .selectable_value(
+                                &mut self.options.backend,
+                                backend,
+                                format!("{backend:?}"),
+                            )
+                            .changed();
+                    }
+                });
+        });
+        match self.options.backend {
+            Backend::Chassis => {
+                ui.horizontal(|ui| {
+                    ui.label("Arcs: ");
+                    for style in [ArcStyle::Chassis, ArcStyle::Wheels] {
+                        if ui
+                            .selectable_label(self.options.arc_style == style, format!("{style:?}"))
+                            .clicked()
+                        {
+                            self.options.arc_style = style;
+                            updated = true;
+                        }
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Drive Units: ");
+                    for unit in [
+                        DriveUnit::Inches,
+                        DriveUnit::Degrees,
+                        DriveUnit::Rotations,
+                        DriveUnit::Ticks,
+                    ] {
+                        updated |= ui
+                            .selectable_value(
+                                &mut self.options.drive_unit,
+                                unit,
+                                format!("{unit:?}"),
+                            )
+                            .on_hover_text(
+                                "Converted using the robot's wheel diameter and gear ratio",
+                            )
+                            .changed();
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Branches: ");
+                    for style in [BranchStyle::IfElse, BranchStyle::Functions] {
+                        updated |= ui
+                            .selectable_value(
+                                &mut self.options.branch_style,
+                                style,
+                                format!("{style:?}"),
+                            )
+                            .on_hover_text(match style {
+                                BranchStyle::IfElse => "Write branches inside if/else blocks",
+                                BranchStyle::Functions => "Write each branch as a function",
+                            })
+                            .changed();
+                    }
+                });
+                egui::CollapsingHeader::new("Templates")
+                    .id_salt("templates")
+                    .show(ui, |ui| updated |= self.templates_ui(ui));
+            }
+            Backend::PursuitArray | Backend::PursuitFile => {
+                let pursuit = &mut self.options.pursuit;
+                egui::Grid::new("pursuit").show(ui, |ui| {
+                    let fields = [
+                        ("Spacing: ", &mut pursuit.spacing, " in"),
+                        (
+                            "Max Acceleration: ",
+                            &mut pursuit.max_acceleration,
+                            " in/s²",
+                        ),
+                        ("Turn Constant: ", &mut pursuit.turn_constant, ""),
+                        ("Lookahead: ", &mut pursuit.lookahead, " in"),
+                    ];
+                    for (label, value, suffix) in fields {
+                        ui.label(label);
+                        updated |= ui
+                            .add(
+                                egui::DragValue::new(value)
+                                    .range(0.01..=f32::MAX)
+                                    .speed(0.1)
+                                    .suffix(suffix),
+                            )
+                            .changed();
+                        ui.end_row();
+                    }
+                });
+            }
+            Backend::TrajectoryCsv | Backend::TrajectoryHeader => {
+                let trajectory = &mut self.options.trajectory;
+                egui::Grid::new("trajectory").show(ui, |ui| {
+                    let fields = [
+                        (
+                            "Max Acceleration: ",
+                            &mut trajectory.max_acceleration,
+                            " in/s²",
+                        ),
+                        ("Time Step: ", &mut trajectory.time_step, " s"),
+                    ];
+                    for (label, value, suffix) in fields {
+                        ui.label(label);
+                        updated |= ui
+                            .add(
+                                egui::DragValue::new(value)
+                                    .range(0.001..=f32::MAX)
+                                    .speed(0.01)
+                                    .suffix(suffix),
+                            )
+                            .changed();
+                        ui.end_row();
+                    }
+                });
+            }
+        }
+        updated
+    }
+    /// Shows the auton's name and where the robot starts, returning whether they changed.
+    fn path_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut updated = false;
+        egui::Grid::new("path").show(ui, |ui| {
+            ui.label("Name: ");
+            ui.add(TextEdit::singleline(&mut self.name).hint_text("Untitled"));
+            ui.end_row();
+            let frame = self.field.frame();
+            let Some(start) = self.path.points().first().map(|p| p.id) else {
+                return;
+            };
+            let Some(start) = self.path.get_mut(start) else {
+                return;
+            };
+            ui.label("Start: ");
+            ui.horizontal(|ui| {
+                updated |= coordinate_ui(ui, start, &frame, false).changed();
+                updated |= coordinate_ui(ui, start, &frame, true).changed();
+            });
+            ui.end_row();
+            ui.label("Start Heading: ");
+            updated |= heading_ui(ui, &mut start.heading, &frame)
+                .on_hover_text("Heading the robot starts at")
+                .changed();
+            ui.end_row();
+        });
+        updated
+    }
+    /// Shows the field definition for editing, returning whether its coordinates changed.
+    fn field_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut updated = false;
+        let mut image_changed = false;
+        let empty = self.path.is_empty();
+        let field = &mut self.field;
+        egui::Grid::new("field").show(ui, |ui| {
+            ui.label("Name: ");
+            ui.text_edit_singleline(&mut field.name);
+            ui.end_row();
+            ui.label("Size: ");
+            ui.add_enabled_ui(empty, |ui| {
+                ui.horizontal(|ui| {
+                    for value in [&mut field.width, &mut field.height] {
+                        updated |= ui
+                            .add(
+                                egui::DragValue::new(value)
+                                    .range(1.0..=f32::INFINITY)
+                                    .suffix(" in"),
+                            )
+                            .changed();
+                    }
+                });
+            })
+            .response
+            .on_hover_text("Width and height of the field")
+            .on_disabled_hover_text("Field size may not be changed once path is created.");
+            ui.end_row();
+            ui.label("Origin: ");
+            egui::ComboBox::from_id_salt("origin")
+                .selected_text(format!("{:?}", field.origin))
+                .show_ui(ui, |ui| {
+                    for origin in [Origin::TopLeft, Origin::BottomLeft, Origin::Center] {
+                        updated |= ui
+                            .selectable_value(&mut field.origin, origin, format!("{origin:?}"))
+                            .changed();
+                    }
+                })
+                .response
+                .on_hover_text(
+                    "Where positions are measured from. Other than the top left, y points up.",
                 );
+            ui.end_row();
+            ui.label("Image: ");
+            let images = [
+                FieldImage::None,
+                FieldImage::PushBack,
+                FieldImage::PushBackSkills,
+            ]
+            .into_iter()
+            .chain(self.images.names().map(|n| FieldImage::Stored(n.into())));
+            egui::ComboBox::from_id_salt("image")
+                .selected_text(field.image.label())
+                .show_ui(ui, |ui| {
+                    for image in images {
+                        let label = image.label().to_string();
+                        if ui
+                            .selectable_value(&mut field.image, image, label)
+                            .changed()
+                        {
+                            field.calibration.clear();
+                            image_changed = true;
+                        }
+                    }
+                })
+                .response
+                .on_hover_text(
+                    "Picture drawn under the path. Drop an image on the field to add one.",
+                );
+            ui.end_row();
+        });
+        if image_changed {
+            self.load_field_overlay();
+        }
+        let field = &mut self.field;
+        ui.label("Obstacles: ");
+        let mut removed = None;
+        for (i, obstacle) in field.obstacles.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                if ui
+                    .small_button("x")
+                    .on_hover_text("Remove obstacle")
+                    .clicked()
+                {
+                    removed = Some(i);
+                }
+                let values = match obstacle {
+                    Obstacle::Rect {
+                        x,
+                        y,
+                        width,
+                        height,
+                    } => vec![("x: ", x), ("y: ", y), ("w: ", width), ("h: ", height)],
+                    Obstacle::Circle { x, y, radius } => {
+                        vec![("x: ", x), ("y: ", y), ("r: ", radius)]
+                    }
+                };
+                for (prefix, value) in values {
+                    ui.add(
+                        egui::DragValue::new(value)
+                            .range(0.0..=f32::INFINITY)
+                            .speed(0.1)
+                            .prefix(prefix),
+                    );
+                }
+            });
+        }
+        if let Some(i) = removed {
+            field.obstacles.remove(i);
+        }
+        ui.horizontal(|ui| {
+            let (x, y) = (field.width / 2.0, field.height / 2.0);
+            if ui.button("Add Rectangle").clicked() {
+                field.obstacles.push(Obstacle::Rect {
+                    x: x - 6.0,
+                    y: y - 6.0,
+                    width: 12.0,
+                    height: 12.0,
+                });
+            }
+            if ui.button("Add Circle").clicked() {
+                field.obstacles.push(Obstacle::Circle { x, y, radius: 6.0 });
             }
-            Background::Skills => {
-                self.overlay = Some(
-                    RetainedImage::from_image_bytes(
-                        "",
-                        include_bytes!("../assets/pushback-skills.png"),
+        });
+        ui.label("Calibration: ");
+        let has_image = self.overlay.is_some();
+        let landmarks = match &mut self.calibrating {
+            Some((landmarks, _)) => landmarks,
+            None => &mut field.calibration,
+        };
+        for (i, landmark) in landmarks.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                ui.label(format!("{}.", i + 1));
+                for (prefix, value) in [("x: ", &mut landmark.x), ("y: ", &mut landmark.y)] {
+                    ui.add(egui::DragValue::new(value).speed(0.1).prefix(prefix));
+                }
+            })
+            .response
+            .on_hover_text("Where this landmark is on the field");
+        }
+        ui.horizontal(|ui| {
+            for count in [2, 4] {
+                if ui
+                    .add_enabled(
+                        has_image,
+                        egui::Button::new(format!("Calibrate {count} Points")),
                     )
-                    .unwrap(),
-                );
+                    .on_hover_text(match count {
+                        2 => "Click two landmarks on the image to line up its scale and rotation",
+                        _ => "Click four landmarks on the image to also correct its perspective",
+                    })
+                    .on_disabled_hover_text("Drop an image on the field to calibrate it")
+                    .clicked()
+                {
+                    self.calibrating = Some((field.corner_landmarks(count), 0));
+                    self.cursor_mode = CursorMode::Calibrate;
+                }
+            }
+            if ui
+                .add_enabled(
+                    !field.calibration.is_empty(),
+                    egui::Button::new("Clear Calibration"),
+                )
+                .on_hover_text("Stretch the image over the whole field")
+                .clicked()
+            {
+                field.calibration.clear();
+            }
+        });
+        ui.horizontal(|ui| {
+            if ui
+                .button("Save Field")
+                .on_hover_text("Save this field to the field list")
+                .clicked()
+            {
+                match self.fields.iter_mut().find(|f| f.name == self.field.name) {
+                    Some(field) => *field = self.field.clone(),
+                    None => self.fields.push(self.field.clone()),
+                }
+            }
+            if ui
+                .button("Delete Field")
+                .on_hover_text("Delete the saved field")
+                .clicked()
+            {
+                self.fields.retain(|f| f.name != self.field.name);
+            }
+            if let FieldImage::Stored(name) = &self.field.image {
+                if ui
+                    .button("Delete Image")
+                    .on_hover_text("Delete the uploaded image from every field")
+                    .clicked()
+                {
+                    self.images.remove(name);
+                    self.field.image = FieldImage::None;
+                    self.field.calibration.clear();
+                    self.load_field_overlay();
+                }
+            }
+        });
+        if updated {
+            self.options.frame = self.field.frame();
+        }
+        updated
+    }
+    /// Draws the robot profile editor, returning true if the robot changed
+    fn robot_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut updated = false;
+        ui.horizontal(|ui| {
+            ui.label("Profile: ");
+            egui::ComboBox::from_id_salt("robot")
+                .selected_text(&self.robot.name)
+                .show_ui(ui, |ui| {
+                    for robot in &self.robots {
+                        if ui
+                            .selectable_label(self.robot == *robot, &robot.name)
+                            .clicked()
+                        {
+                            self.robot = robot.clone();
+                            updated = true;
+                        }
+                    }
+                });
+            if ui
+                .button("Save")
+                .on_hover_text("Save this robot as a profile")
+                .clicked()
+            {
+                match self.robots.iter_mut().find(|r| r.name == self.robot.name) {
+                    Some(robot) => *robot = self.robot.clone(),
+                    None => self.robots.push(self.robot.clone()),
+                }
+            }
+            if ui
+                .button("Delete")
+                .on_hover_text("Delete the saved profile")
+                .clicked()
+            {
+                self.robots.retain(|r| r.name != self.robot.name);
+            }
+        });
+        egui::Grid::new("robot").show(ui, |ui| {
+            ui.label("Name: ");
+            ui.text_edit_singleline(&mut self.robot.name);
+            ui.end_row();
+            ui.label("Drivetrain: ");
+            ui.horizontal(|ui| {
+                for drivetrain in [Drivetrain::Tank, Drivetrain::Holonomic, Drivetrain::XDrive] {
+                    updated |= ui
+                        .selectable_value(
+                            &mut self.robot.drivetrain,
+                            drivetrain,
+                            format!("{drivetrain:?}"),
+                        )
+                        .changed();
+                }
+            });
+            ui.end_row();
+            let fields = [
+                ("Track Width: ", &mut self.robot.track_width, " in"),
+                ("Wheel Diameter: ", &mut self.robot.wheel_diameter, " in"),
+                ("Gear Ratio: ", &mut self.robot.gear_ratio, ""),
+                ("Max RPM: ", &mut self.robot.max_rpm, " rpm"),
+                (
+                    "Encoder Ticks: ",
+                    &mut self.robot.ticks_per_rotation,
+                    " per rotation",
+                ),
+                ("Width: ", &mut self.robot.width, " in"),
+                ("Length: ", &mut self.robot.length, " in"),
+            ];
+            for (label, value, suffix) in fields {
+                ui.label(label);
+                updated |= ui
+                    .add(
+                        egui::DragValue::new(value)
+                            .range(0.01..=f32::MAX)
+                            .speed(0.05)
+                            .suffix(suffix),
+                    )
+                    .changed();
+                ui.end_row();
+            }
+        });
+        updated
+    }
+    /// Shows the chassis code templates for editing, returning whether they changed.
+    fn templates_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut updated = false;
+        let templates = &mut self.options.templates;
+        let code = |text| TextEdit::multiline(text).font(egui::FontId::monospace(12.0));
+        egui::Grid::new("templates").show(ui, |ui| {
+            let sections = [
+                ("Header: ", &mut templates.header, "Text before the code"),
+                (
+                    "Wrapper: ",
+                    &mut templates.wrapper,
+                    "Text around the code, which replaces {body}",
+                ),
+                ("Footer: ", &mut templates.footer, "Text after the code"),
+            ];
+            for (label, text, hover) in sections {
+                ui.label(label);
+                updated |= ui
+                    .add(code(text).desired_rows(2))
+                    .on_hover_text(hover)
+                    .changed();
+                ui.end_row();
+            }
+            let commands = [
+                ("Drive: ", &mut templates.drive, "{distance}"),
+                ("Turn: ", &mut templates.turn, "{angle}"),
+                ("Arc: ", &mut templates.arc, "{radius}, {angle}"),
+                ("Tank Arc: ", &mut templates.tank, "{left}, {right}, {radius}, {angle}"),
+                ("Speed: ", &mut templates.speed, "{speed}"),
+                ("Marker: ", &mut templates.marker, "{code}"),
+            ];
+            for (label, text, placeholders) in commands {
+                ui.label(label);
+                updated |= ui
+                    .add(code(text).desired_rows(1))
+                    .on_hover_text(format!(
+                        "Placeholders: {placeholders}, and {{x}}, {{y}}, {{heading}} after the command"
+                    ))
+                    .changed();
+                ui.end_row();
+            }
+            ui.label("Precision: ");
+            updated |= ui
+                .add(egui::DragValue::new(&mut templates.precision).range(0..=6))
+                .on_hover_text("Decimal places numbers are written with")
+                .changed();
+            ui.end_row();
+        });
+        if ui.button("Reset Templates").clicked() {
+            *templates = Templates::default();
+            updated = true;
+        }
+        updated
+    }
+    /// Shows the generated code. Chassis code can be edited, and is read back into the path
+    /// as it's typed without reformatting it, so hand-written autons can be visualized.
+    fn code_ui(&mut self, ui: &mut egui::Ui) {
+        let editor = |text| {
+            TextEdit::multiline(text)
+                .font(egui::FontId::monospace(12.0))
+                .desired_width(f32::INFINITY)
+        };
+        if self.options.backend != Backend::Chassis {
+            ui.add(editor(&mut self.generated.clone()));
+            return;
+        }
+        if !self.path.branches().is_empty() {
+            ui.add(editor(&mut self.generated.clone()))
+                .on_hover_text("Paths with branches can't be edited as code");
+            return;
+        }
+        if !self.options.templates.default_commands() {
+            ui.add(editor(&mut self.generated.clone())).on_hover_text(
+                "Only code written with the default command templates can be edited",
+            );
+            return;
+        }
+        let response = ui
+            .add(editor(&mut self.generated))
+            .on_hover_text("Edit or paste chassis code to redraw the path");
+        if response.changed() {
+            // Paths pasted onto an empty field start in the middle
+            let start = if self.path.is_empty() {
+                Path::new(vec![Point::new(
+                    self.field.width / 2.0,
+                    self.field.height / 2.0,
+                )])
+            } else {
+                self.path.clone()
+            };
+            match parse(&self.generated, &start, &self.robot, &self.options) {
+                Ok(path) => {
+                    self.path = path;
+                    self.code_error = None;
+                }
+                Err(err) => self.code_error = Some(err),
+            }
+        }
+        if let Some(err) = &self.code_error {
+            ui.colored_label(ui.visuals().warn_fg_color, err);
+        }
+    }
+    /// Shows the cursor's field position, and the hovered segment with the code it generates.
+    fn status_ui(&self, ui: &mut egui::Ui) {
+        let ratio = self.ratio();
+        let Some(pos) = ui
+            .ctx()
+            .pointer_hover_pos()
+            .filter(|pos| self.field_rect.contains(*pos))
+            .map(|pos| ((pos - self.field_rect.min) / ratio).to_pos2())
+        else {
+            ui.label("Hover over the field to see positions");
+            return;
+        };
+        let shown = self.options.frame.to_frame(pos);
+        ui.label(format!("X: {:.2} in  Y: {:.2} in", shown.x, shown.y));
+        if !self.field.is_clear(pos) {
+            ui.colored_label(ui.visuals().warn_fg_color, "Blocked");
+        }
+
+        // Segments within a few pixels of the cursor count as hovered
+        let reach = 6.0 / ratio;
+        let distance = |segment: &Segment| {
+            let steps = (segment.length() / 0.5).ceil().max(1.0) as usize;
+            (0..=steps)
+                .map(|i| segment.sample(i as f32 / steps as f32).distance(pos))
+                .fold(f32::INFINITY, f32::min)
+        };
+        // Code is formatted for the whole path, since templates can use the robot's position
+        let segments = segment_commands(self.path.points());
+        let all: Vec<Command> = segments.iter().flat_map(|(_, c)| c.clone()).collect();
+        let mut lines =
+            format_commands(self.path.points(), &all, &self.robot, &self.options).into_iter();
+        let hovered = segments
+            .into_iter()
+            .map(|(segment, commands)| {
+                let code: Vec<String> = lines.by_ref().take(commands.len()).collect();
+                (distance(&segment), segment, code)
+            })
+            .filter(|(d, ..)| *d < reach)
+            .min_by(|a, b| a.0.total_cmp(&b.0));
+        if let Some((_, segment, code)) = hovered {
+            ui.separator();
+            let heading = |t: f32| {
+                let direction =
+                    segment.sample((t + 0.01).min(1.0)) - segment.sample((t - 0.01).max(0.0));
+                self.options.frame.heading(direction.angle().to_degrees())
+            };
+            match segment {
+                Segment::Line { .. } => ui.label(format!(
+                    "Line: {:.2} in, heading {:.1}°",
+                    segment.length(),
+                    heading(0.0)
+                )),
+                Segment::Arc { radius, .. } => ui.label(format!(
+                    "Arc: {:.2} in, radius {radius:.2} in, heading {:.1}° to {:.1}°",
+                    segment.length(),
+                    heading(0.0),
+                    heading(1.0)
+                )),
+            };
+            ui.separator();
+            let code: Vec<&str> = code.iter().map(|line| line.trim()).collect();
+            ui.monospace(code.join(" "));
+        }
+    }
+    /// Lists the path's branches for editing, returning whether any changed
+    fn branches_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut updated = false;
+        let frame = self.field.frame();
+        let points = self.path.points().to_vec();
+        let label = |id: Uuid| {
+            points
+                .iter()
+                .position(|p| p.id == id)
+                .map_or("-".into(), |i| {
+                    let pos = frame.to_frame(pos2(points[i].x, points[i].y));
+                    format!("Point {} ({:.1}, {:.1})", i + 1, pos.x, pos.y)
+                })
+        };
+        let mut removed = None;
+        for (i, branch) in self.path.branches_mut().iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                if ui
+                    .small_button("x")
+                    .on_hover_text("Remove branch")
+                    .clicked()
+                {
+                    removed = Some(i);
+                }
+                let drawing = self.branching == Some(i);
+                if ui
+                    .selectable_label(drawing, "Draw")
+                    .on_hover_text("Add created points to this branch instead of the main path")
+                    .clicked()
+                {
+                    self.branching = (!drawing).then_some(i);
+                    if !drawing {
+                        self.cursor_mode = CursorMode::Create;
+                    }
+                }
+                ui.colored_label(BRANCH_COLOR, format!("From {}", label(branch.from)));
+            });
+            egui::Grid::new(("branch", i)).show(ui, |ui| {
+                ui.label("Name: ");
+                updated |= ui.text_edit_singleline(&mut branch.name).changed();
+                ui.end_row();
+                ui.label("If: ");
+                updated |= ui
+                    .text_edit_singleline(&mut branch.condition)
+                    .on_hover_text("Condition checked on reaching the branch")
+                    .changed();
+                ui.end_row();
+                ui.label("Rejoin: ");
+                let from = points.iter().position(|p| p.id == branch.from).unwrap_or(0);
+                egui::ComboBox::from_id_salt(("rejoin", i))
+                    .selected_text(branch.rejoin.map_or("Never".into(), label))
+                    .show_ui(ui, |ui| {
+                        updated |= ui
+                            .selectable_value(&mut branch.rejoin, None, "Never")
+                            .changed();
+                        for point in &points[from..] {
+                            updated |= ui
+                                .selectable_value(&mut branch.rejoin, Some(point.id), label(point.id))
+                                .changed();
+                        }
+                    })
+                    .response
+                    .on_hover_text(
+                        "Main path point the branch drives back to. Without one, the run ends with the branch.",
+                    );
+                ui.end_row();
+            });
+        }
+        if let Some(i) = removed {
+            self.path.branches_mut().remove(i);
+            self.branching = None;
+            updated = true;
+        }
+        if self.path.branches().is_empty() {
+            ui.label("Add a branch from a point in the Point Inspector");
+        }
+        updated
+    }
+    /// Lists logged runs and how far each strayed from the path
+    fn telemetry_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if ui
+                .button("Import Log")
+                .on_hover_text(
+                    "Read a CSV or JSON odometry log pasted into Save Data. Logs can also be dropped on the field.",
+                )
+                .clicked()
+            {
+                let name = format!("Run {}", self.runs.len() + 1);
+                self.import_log(&name, &self.save_data.clone());
+            }
+            if ui
+                .add_enabled(!self.runs.is_empty(), egui::Button::new("Clear Runs"))
+                .clicked()
+            {
+                self.runs.clear();
+            }
+        });
+        let points = self.path.points();
+        let mut removed = None;
+        for (i, run) in self.runs.iter().enumerate() {
+            ui.horizontal(|ui| {
+                if ui.small_button("x").on_hover_text("Remove run").clicked() {
+                    removed = Some(i);
+                }
+                ui.colored_label(run_color(i), &run.name);
+                let end =
+                    end_error(points, &run.poses).map_or("-".to_string(), |e| format!("{e:.2} in"));
+                ui.label(format!(
+                    "{:.2} s, ended {end} from the last point",
+                    run.duration()
+                ));
+            });
+            egui::CollapsingHeader::new("Deviation")
+                .id_salt(("deviation", i))
+                .show(ui, |ui| {
+                    egui::Grid::new(("deviation_grid", i))
+                        .striped(true)
+                        .show(ui, |ui| {
+                            for header in ["Segment", "Poses", "Mean", "Max"] {
+                                ui.label(header);
+                            }
+                            ui.end_row();
+                            for (j, deviation) in deviations(points, &run.poses).iter().enumerate()
+                            {
+                                ui.label(format!("{} to {}", j + 1, j + 2));
+                                ui.label(deviation.samples.to_string());
+                                for value in [deviation.mean, deviation.max] {
+                                    ui.label(if deviation.samples > 0 {
+                                        format!("{value:.2} in")
+                                    } else {
+                                        "-".into()
+                                    });
+                                }
+                                ui.end_row();
+                            }
+                        });
+                });
+        }
+        if let Some(i) = removed {
+            self.runs.remove(i);
+        }
+    }
+    /// Saves versions of the path and shows what changed since one of them
+    fn compare_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if ui
+                .button("Save Version")
+                .on_hover_text("Keep a copy of the path and settings to compare against later")
+                .clicked()
+            {
+                self.versions_saved += 1;
+                self.history.push(Version {
+                    label: format!("Version {}", self.versions_saved),
+                    project: self.project(),
+                });
+                if self.history.len() > HISTORY_LENGTH {
+                    self.history.remove(0);
+                }
             }
-            Background::Custom => {
-                self.overlay = self
-                    .uploaded
-                    .as_ref()
-                    .and_then(|bytes| RetainedImage::from_image_bytes("", bytes).ok());
+            let selected = self.compare.as_ref().map_or("Nothing", |v| &v.label);
+            let mut chosen = None;
+            egui::ComboBox::from_id_salt("compare")
+                .selected_text(selected.to_string())
+                .show_ui(ui, |ui| {
+                    if ui
+                        .selectable_label(selected == "Nothing", "Nothing")
+                        .clicked()
+                    {
+                        chosen = Some(None);
+                    }
+                    for version in self.history.iter().rev() {
+                        if ui
+                            .selectable_label(selected == version.label, &version.label)
+                            .clicked()
+                        {
+                            chosen = Some(Some(version.clone()));
+                        }
+                    }
+                    if ui
+                        .selectable_label(false, "Save Data")
+                        .on_hover_text("Compare with the project in Save Data")
+                        .clicked()
+                    {
+                        match Project::from_json(&self.save_data) {
+                            Ok(project) => {
+                                chosen = Some(Some(Version {
+                                    label: "Save Data".into(),
+                                    project,
+                                }))
+                            }
+                            Err(e) => self.message = Some(format!("Invalid save data: {e}")),
+                        }
+                    }
+                })
+                .response
+                .on_hover_text("Version drawn as a ghost under the path");
+            if let Some(chosen) = chosen {
+                self.compare = chosen;
             }
+            if let Some(version) = &self.compare {
+                if ui
+                    .button("Restore")
+                    .on_hover_text("Go back to this version")
+                    .clicked()
+                {
+                    let project = version.project.clone();
+                    self.name = project.name;
+                    self.path = project.path;
+                    self.robot = project.robot;
+                    self.options = project.options;
+                    self.generate();
+                }
+            }
+        });
+        let Some(version) = &self.compare else {
+            return;
+        };
+        let frame = self.field.frame();
+        let old = version.project.path.points();
+        let changes = waypoint_changes(old, self.path.points());
+        if changes.is_empty() {
+            ui.label("No waypoints changed");
+        } else {
+            egui::Grid::new("waypoint_changes")
+                .striped(true)
+                .show(ui, |ui| {
+                    for header in ["Waypoint", "Before", "After"] {
+                        ui.label(header);
+                    }
+                    ui.end_row();
+                    for change in &changes {
+                        let (label, before, after) = match change {
+                            WaypointChange::Added(i, point) => {
+                                (format!("{} added", i + 1), None, Some(point))
+                            }
+                            WaypointChange::Removed(i, point) => {
+                                (format!("{} removed", i + 1), Some(point), None)
+                            }
+                            WaypointChange::Changed { before, after } => (
+                                format!("{} changed", after.0 + 1),
+                                Some(&before.1),
+                                Some(&after.1),
+                            ),
+                        };
+                        ui.label(label);
+                        for point in [before, after] {
+                            ui.label(point.map_or("-".into(), |p| describe_point(p, &frame)));
+                        }
+                        ui.end_row();
+                    }
+                });
         }
+        let lines = line_changes(
+            &command_lines(old, &version.project.robot, &version.project.options),
+            &command_lines(self.path.points(), &self.robot, &self.options),
+        );
+        egui::CollapsingHeader::new("Commands")
+            .default_open(true)
+            .show(ui, |ui| {
+                let mut changed = false;
+                for line in &lines {
+                    let (text, color) = match line {
+                        LineChange::Same(_) => continue,
+                        LineChange::Added(line) => (format!("+ {line}"), Color32::GREEN),
+                        LineChange::Removed(line) => (format!("- {line}"), Color32::RED),
+                    };
+                    ui.colored_label(color, egui::RichText::new(text).monospace());
+                    changed = true;
+                }
+                if !changed {
+                    ui.label("No commands changed");
+                }
+            });
     }
-    /// Gets the Bezier points in their save state
-    fn get_save(&self) -> Vec<Point> {
-        self.points.iter().map(|p| p.borrow().clone()).collect()
+    /// Draws the playback controls
+    fn playback_ui(&mut self, ui: &mut egui::Ui) {
+        let path = waypoints(self.path.points());
+        let samples = sample(&path, &self.robot, &self.options.pursuit);
+        let total = samples.last().map(|p| p.distance).unwrap_or(0.0);
+        let time = self.robot.path_time(
+            &commands(self.path.points()),
+            self.options.trajectory.max_acceleration,
+        );
+        ui.label(format!("Estimated time: {time:.1} s"))
+            .on_hover_text("Driving each command from rest to rest at the robot's top speed");
+        ui.horizontal(|ui| {
+            ui.add_enabled_ui(total > 0.0, |ui| {
+                if ui
+                    .button(if self.playing { "Pause" } else { "Play" })
+                    .clicked()
+                {
+                    self.playing = !self.playing;
+                    if self.playback.map_or(true, |d| d >= total) {
+                        self.playback = Some(0.0);
+                    }
+                }
+                if ui.button("Stop").clicked() {
+                    self.playing = false;
+                    self.playback = None;
+                }
+                if let Some(distance) = &mut self.playback {
+                    ui.add(egui::Slider::new(distance, 0.0..=total).suffix(" in"));
+                }
+            });
+        });
     }
-    /// Loads saved Bezier points
-    fn load_save(&mut self, save: &Vec<Point>) {
-        self.points = save
-            .iter()
-            .map(|p| Rc::new(RefCell::new(p.clone())))
+    /// Switches to a cursor mode, or back to the default if it's already on.
+    fn toggle_mode(&mut self, mode: CursorMode) {
+        if self.cursor_mode != mode {
+            self.cursor_mode = mode;
+        } else {
+            self.cursor_mode = CursorMode::Default;
+        }
+    }
+    /// Does what an action says, whether from a shortcut, the palette or a button.
+    fn run(&mut self, action: Action) {
+        match action {
+            Action::Create => self.toggle_mode(CursorMode::Create),
+            Action::Insert => self.toggle_mode(CursorMode::Insert),
+            Action::Delete => self.toggle_mode(CursorMode::Delete),
+            Action::Trim => self.toggle_mode(CursorMode::Trim),
+            Action::Measure => self.toggle_mode(CursorMode::Measure),
+            Action::Generate => self.generate(),
+            Action::Clear => {
+                self.path.clear();
+                self.generate();
+            }
+            Action::Mirror => {
+                self.path.mirror(self.field.width);
+                self.generate();
+            }
+            Action::ExportPng => self.export_picture(false),
+            Action::ExportSvg => self.export_picture(true),
+            Action::ExportReport => self.export_report(),
+            Action::Undo => self.step_history(false),
+            Action::Redo => self.step_history(true),
+            Action::Palette => self.palette = Some((String::new(), 0)),
+            Action::NudgeLeft => self.nudge(Vec2::LEFT, NUDGE),
+            Action::NudgeRight => self.nudge(Vec2::RIGHT, NUDGE),
+            Action::NudgeUp => self.nudge(Vec2::UP, NUDGE),
+            Action::NudgeDown => self.nudge(Vec2::DOWN, NUDGE),
+            Action::NudgeLeftFine => self.nudge(Vec2::LEFT, NUDGE_FINE),
+            Action::NudgeRightFine => self.nudge(Vec2::RIGHT, NUDGE_FINE),
+            Action::NudgeUpFine => self.nudge(Vec2::UP, NUDGE_FINE),
+            Action::NudgeDownFine => self.nudge(Vec2::DOWN, NUDGE_FINE),
+            Action::NudgeLeftCoarse => self.nudge(Vec2::LEFT, NUDGE_COARSE),
+            Action::NudgeRightCoarse => self.nudge(Vec2::RIGHT, NUDGE_COARSE),
+            Action::NudgeUpCoarse => self.nudge(Vec2::UP, NUDGE_COARSE),
+            Action::NudgeDownCoarse => self.nudge(Vec2::DOWN, NUDGE_COARSE),
+            Action::NextPoint => self.cycle_point(false),
+            Action::PreviousPoint => self.cycle_point(true),
+            Action::DeletePoint => {
+                if let Some(id) = self.inspecting.take() {
+                    self.path.remove(id);
+                    self.generate();
+                }
+            }
+            Action::Cancel => {
+                self.cursor_mode = CursorMode::Default;
+                self.measure = None;
+                self.calibrating = None;
+                self.branching = None;
+            }
+        }
+    }
+    /// Text describing an action and its shortcut, for hovering buttons.
+    fn action_hint(&self, ctx: &egui::Context, action: Action) -> String {
+        match self.keymap.shortcut(action) {
+            Some(shortcut) => format!(
+                "{} ({})",
+                action.description(),
+                ctx.format_shortcut(&shortcut)
+            ),
+            None => action.description().to_string(),
+        }
+    }
+    /// Goes back to the path before the last change, or forward again for `redo`.
+    fn step_history(&mut self, redo: bool) {
+        let (from, to) = if redo {
+            (&mut self.redo, &mut self.undo)
+        } else {
+            (&mut self.undo, &mut self.redo)
+        };
+        if let Some(path) = from.pop() {
+            to.push(std::mem::replace(&mut self.path, path));
+            self.undo_base = Some(self.path.clone());
+            self.generate();
+        }
+    }
+    /// Saves the path for undoing once it changes.
+    /// Waits for the mouse to be let go, so a whole drag is undone at once.
+    fn track_history(&mut self, ctx: &egui::Context) {
+        if ctx.input(|i| i.pointer.any_down()) || self.undo_base.as_ref() == Some(&self.path) {
+            return;
+        }
+        if let Some(base) = self.undo_base.replace(self.path.clone()) {
+            self.undo.push(base);
+            if self.undo.len() > UNDO_LENGTH {
+                self.undo.remove(0);
+            }
+            self.redo.clear();
+        }
+    }
+    /// Reads the next key pressed as the new shortcut for the action being rebound.
+    /// Escape cancels instead.
+    fn rebind(&mut self, ctx: &egui::Context) {
+        let Some(action) = self.rebinding else {
+            return;
+        };
+        let pressed = ctx.input_mut(|input| {
+            let (key, modifiers) = input.events.iter().find_map(|event| match *event {
+                egui::Event::Key {
+                    key,
+                    pressed: true,
+                    modifiers,
+                    ..
+                } => Some((key, modifiers)),
+                _ => None,
+            })?;
+            input.consume_key(modifiers, key);
+            Some(egui::KeyboardShortcut::new(modifiers, key))
+        });
+        if let Some(shortcut) = pressed {
+            if shortcut.logical_key != egui::Key::Escape {
+                self.keymap.bind(action, Some(shortcut));
+            }
+            self.rebinding = None;
+        }
+    }
+    /// Draws every action's shortcut, with buttons to rebind or remove it.
+    fn shortcuts_ui(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("shortcuts").show(ui, |ui| {
+            for action in Action::ALL {
+                ui.label(action.name()).on_hover_text(action.description());
+                let text = if self.rebinding == Some(action) {
+                    "Press a key...".to_string()
+                } else {
+                    self.keymap
+                        .shortcut(action)
+                        .map_or("None".into(), |s| ui.ctx().format_shortcut(&s))
+                };
+                if ui
+                    .button(text)
+                    .on_hover_text("Click, then press the new shortcut, or Escape to cancel")
+                    .clicked()
+                {
+                    self.rebinding = Some(action);
+                }
+                if ui
+                    .add_enabled(
+                        self.keymap.shortcut(action).is_some(),
+                        egui::Button::new("x").small(),
+                    )
+                    .on_hover_text("Remove the shortcut")
+                    .clicked()
+                {
+                    self.keymap.bind(action, None);
+                }
+                ui.end_row();
+            }
+        });
+        if ui
+            .button("Reset Shortcuts")
+            .on_hover_text("Go back to the default shortcuts")
+            .clicked()
+        {
+            self.keymap.reset();
+            self.rebinding = None;
+        }
+    }
+    /// Searchable list of every action, run with Enter or a click.
+    fn palette_ui(&mut self, ctx: &egui::Context) {
+        let Some((query, selected)) = &mut self.palette else {
+            return;
+        };
+        let search = query.to_lowercase();
+        let matches: Vec<Action> = Action::ALL
+            .into_iter()
+            .filter(|a| a.name().to_lowercase().contains(&search))
             .collect();
+        let (mut chosen, mut close) = (None, false);
+        let id = egui::Id::new("palette");
+        let area =
+            egui::Modal::default_area(id).anchor(egui::Align2::CENTER_TOP, Vec2::new(0.0, 60.0));
+        let modal = egui::Modal::new(id).area(area).show(ctx, |ui| {
+            let response = ui.add(
+                TextEdit::singleline(query)
+                    .hint_text("Search commands")
+                    .desired_width(320.0),
+            );
+            response.request_focus();
+            if response.changed() {
+                *selected = 0;
+            }
+            ctx.input(|input| {
+                if input.key_pressed(egui::Key::ArrowDown) {
+                    *selected = (*selected + 1).min(matches.len().saturating_sub(1));
+                }
+                if input.key_pressed(egui::Key::ArrowUp) {
+                    *selected = selected.saturating_sub(1);
+                }
+                if input.key_pressed(egui::Key::Enter) {
+                    chosen = matches.get(*selected).copied();
+                    close = true;
+                }
+            });
+            for (i, &action) in matches.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    if ui
+                        .selectable_label(i == *selected, action.name())
+                        .on_hover_text(action.description())
+                        .clicked()
+                    {
+                        chosen = Some(action);
+                    }
+                    if let Some(shortcut) = self.keymap.shortcut(action) {
+                        ui.weak(ctx.format_shortcut(&shortcut));
+                    }
+                });
+            }
+            if matches.is_empty() {
+                ui.weak("No matching commands");
+            }
+        });
+        if close || modal.should_close() || chosen.is_some() {
+            self.palette = None;
+        }
+        if let Some(action) = chosen {
+            self.run(action);
+        }
+    }
+    /// Inspects the next waypoint, main path first, or the previous one going `backwards`.
+    fn cycle_point(&mut self, backwards: bool) {
+        let ids: Vec<Uuid> = self.path.all_points().map(|p| p.id).collect();
+        if ids.is_empty() {
+            return;
+        }
+        let current = self
+            .inspecting
+            .and_then(|id| ids.iter().position(|i| *i == id));
+        let index = match (current, backwards) {
+            (Some(i), false) => (i + 1) % ids.len(),
+            (Some(i), true) => (i + ids.len() - 1) % ids.len(),
+            (None, false) => 0,
+            (None, true) => ids.len() - 1,
+        };
+        self.inspecting = Some(ids[index]);
+    }
+    /// Moves the inspected point by `step` inches in a direction.
+    fn nudge(&mut self, direction: Vec2, step: f32) {
+        if let Some(point) = self.inspecting.and_then(|id| self.path.get_mut(id)) {
+            point.offset(direction.x * step, direction.y * step);
+            self.generate();
+        }
     }
 }
 
@@ -198,12 +1570,28 @@ impl eframe::App for PathyApp {
     fn save(&mut self, storage: &mut dyn eframe::Storage) {
         // save app state
         eframe::set_value(storage, eframe::APP_KEY, self);
-        eframe::set_value(storage, "path", &self.get_save());
+        eframe::set_value(storage, "path", &self.path);
+    }
+
+    /// Runs the actions for shortcuts pressed before egui sees them, unless a text field has focus.
+    fn raw_input_hook(&mut self, ctx: &egui::Context, raw_input: &mut egui::RawInput) {
+        // Focus is checked before Escape clears it
+        if ctx.wants_keyboard_input() || self.rebinding.is_some() || self.palette.is_some() {
+            return;
+        }
+        for action in self.keymap.pressed(&mut raw_input.events) {
+            self.run(action);
+        }
     }
 
     /// Called each time the UI needs repainting, which may be many times per second.
     #[allow(deprecated)]
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // Images saved in the browser load in after startup
+        if self.images.poll() {
+            self.load_field_overlay();
+        }
+        self.rebind(ctx);
         // Put your widgets into a `SidePanel`, `TopBottomPanel`, `CentralPanel`, `Window` or `Area`.
         // For inspiration and more examples, go to https://emilk.github.io/egui
 
@@ -213,12 +1601,6 @@ impl eframe::App for PathyApp {
             egui::menu::bar(ui, |ui| {
                 ui.label("Pathy v2.1.0");
                 ui.separator();
-                ui.label("Field Size: ");
-                ui.add_enabled_ui(self.points.is_empty(), |ui| {
-                    ui.add(egui::DragValue::new(&mut self.size).suffix(" inches"));
-                })
-                .response
-                .on_disabled_hover_text("Field size may not be changed once path is created.");
                 ui.label("Field Scale: ");
                 ui.add(
                     egui::DragValue::new(&mut self.scale)
@@ -230,65 +1612,68 @@ impl eframe::App for PathyApp {
                 ui.separator();
                 /* BUTTON LOGIC */
                 let modes = [
-                    (egui::Key::C, CursorMode::Create, "Create new point"),
-                    (egui::Key::I, CursorMode::Insert, "Insert point in path"),
-                    (egui::Key::D, CursorMode::Delete, "Delete a single point"),
-                    (egui::Key::T, CursorMode::Trim, "Trim path to point"),
+                    (Action::Create, CursorMode::Create),
+                    (Action::Insert, CursorMode::Insert),
+                    (Action::Delete, CursorMode::Delete),
+                    (Action::Trim, CursorMode::Trim),
+                    (Action::Measure, CursorMode::Measure),
                 ];
                 // Custom selectable label lets us double click to return to default
-                for (key, mode, desc) in modes {
+                for (action, mode) in modes {
                     if ui
                         .add(egui::SelectableLabel::new(
                             self.cursor_mode == mode,
                             format!("{mode:?}"), // since we derive debug
                         ))
-                        .on_hover_text(format!("{desc} ({})", format!("{:?}", key).to_lowercase()))
+                        .on_hover_text(self.action_hint(ctx, action))
                         .clicked()
                     {
-                        if self.cursor_mode != mode {
-                            self.cursor_mode = mode.clone();
-                        } else {
-                            self.cursor_mode = CursorMode::Default;
-                        }
+                        self.run(action);
                     }
-                    // also check key press
-                    ctx.input(|input| {
-                        if input.key_pressed(key) {
-                            if self.cursor_mode != mode {
-                                self.cursor_mode = mode;
-                            } else {
-                                self.cursor_mode = CursorMode::Default;
-                            }
-                        }
-                    });
                 }
                 ui.separator();
-                if ui
-                    .button("Generate")
-                    .on_hover_text("Generate path code")
-                    .clicked()
-                {
-                    self.generate();
-                };
-                if ui.button("Clear").on_hover_text("Clear path").clicked() {
-                    self.points.clear();
-                    self.generate();
-                };
-                ui.separator();
-                ui.label("Field: ");
-                // store functions to lazily load images
-                // do NOT load these every frame
-                let fields = [Background::Game, Background::Skills, Background::Custom];
-                for field in fields {
+                for (action, label) in [
+                    (Action::Generate, "Generate"),
+                    (Action::Clear, "Clear"),
+                    (Action::Mirror, "Mirror"),
+                    (Action::Undo, "Undo"),
+                    (Action::Redo, "Redo"),
+                    (Action::Palette, "Commands"),
+                ] {
                     if ui
-                        .add(egui::SelectableLabel::new(
-                            self.background == field,
-                            format!("{field:?}"),
-                        ))
+                        .button(label)
+                        .on_hover_text(self.action_hint(ctx, action))
                         .clicked()
                     {
-                        self.background = field;
-                        self.load_field_overlay();
+                        self.run(action);
+                    }
+                }
+                ui.separator();
+                ui.label("Field: ");
+                // Only load images on selection, never every frame
+                let mut chosen = None;
+                egui::ComboBox::from_id_salt("field")
+                    .selected_text(&self.field.name)
+                    .show_ui(ui, |ui| {
+                        for field in Field::builtin().into_iter().chain(self.fields.clone()) {
+                            if ui
+                                .selectable_label(self.field.name == field.name, &field.name)
+                                .clicked()
+                            {
+                                chosen = Some(field);
+                            }
+                        }
+                    });
+                if let Some(field) = chosen {
+                    // Sizes can't change under an existing path
+                    if self.path.is_empty()
+                        || (field.width, field.height) == (self.field.width, self.field.height)
+                    {
+                        self.set_field(field);
+                    } else {
+                        self.message = Some(
+                            "Clear the path before switching to a field of another size".into(),
+                        );
                     }
                 }
 
@@ -299,58 +1684,156 @@ impl eframe::App for PathyApp {
             });
         });
 
+        egui::TopBottomPanel::bottom("status").show(ctx, |ui| {
+            ui.horizontal(|ui| self.status_ui(ui));
+        });
+
         egui::SidePanel::right("side").show(ctx, |ui| {
             ui.with_layout(egui::Layout::top_down(egui::Align::RIGHT), |ui| {
                 egui::ScrollArea::vertical().show(ui, |ui| {
                     let mut updated = false;
-                    if let Some(point_ref) = &self.inspecting.clone() {
-                        let mut point = point_ref.borrow_mut();
+                    ui.label("Path");
+                    ui.separator();
+                    updated |= self.path_ui(ui);
+                    let frame = self.field.frame();
+                    if let Some(point) = self.inspecting.and_then(|id| self.path.get_mut(id)) {
                         ui.label("Point Inspector");
                         ui.separator();
-                        ui.with_layout(egui::Layout::top_down(egui::Align::LEFT), |ui| {
-                            ui.horizontal(|ui| {
-                                ui.label("X: ");
-                                let x = point.x;
-                                let mut text = format!("{x:.3}");
-                                if ui.text_edit_singleline(&mut text).has_focus() {
-                                    point.x = text.parse().unwrap_or(x);
-                                    updated = true;
-                                }
+                        updated |= point_ui(ui, point, &frame);
+                        let id = point.id;
+                        if self.path.index_of(id).is_some()
+                            && ui
+                                .button("Add Branch")
+                                .on_hover_text("Branch off from this point when a condition holds")
+                                .clicked()
+                        {
+                            let branches = self.path.branches_mut();
+                            // The first number not already naming a branch
+                            let name = (1..)
+                                .map(|n| format!("branch_{n}"))
+                                .find(|name| branches.iter().all(|b| &b.name != name))
+                                .unwrap_or_default();
+                            branches.push(Branch {
+                                name,
+                                from: id,
+                                ..Default::default()
                             });
-                            ui.horizontal(|ui| {
-                                ui.label("Y: ");
-                                let y = point.y;
-                                let mut text = format!("{y:.3}");
-                                if ui.text_edit_singleline(&mut text).has_focus() {
-                                    point.y = text.parse().unwrap_or(y);
-                                    updated = true;
-                                }
-                            });
-                        });
+                            self.branching = Some(branches.len() - 1);
+                            self.cursor_mode = CursorMode::Create;
+                            updated = true;
+                        }
                     }
+                    ui.label("Branches");
+                    ui.separator();
+                    updated |= self.branches_ui(ui);
+                    ui.label("Field");
+                    ui.separator();
+                    updated |= self.field_ui(ui);
+                    ui.label("Robot");
+                    ui.separator();
+                    updated |= self.robot_ui(ui);
+                    ui.label("Code");
+                    ui.separator();
+                    updated |= self.options_ui(ui);
                     if updated {
                         self.generate();
                     }
-                    ui.label("Code");
+                    self.code_ui(ui);
+                    ui.label("Playback");
                     ui.separator();
-                    ui.add(
-                        TextEdit::multiline(&mut self.generated.clone())
-                            .font(egui::FontId::monospace(12.0))
-                            .desired_width(f32::INFINITY),
-                    );
+                    self.playback_ui(ui);
+                    ui.label("Telemetry");
+                    ui.separator();
+                    self.telemetry_ui(ui);
+                    ui.label("Compare");
+                    ui.separator();
+                    self.compare_ui(ui);
+                    ui.label("Export");
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            egui::DragValue::new(&mut self.export_size)
+                                .range(64..=8192)
+                                .suffix("px"),
+                        )
+                        .on_hover_text("Width of the exported picture");
+                        ui.checkbox(&mut self.export_footprints, "Robot")
+                            .on_hover_text("Draw the robot at every waypoint");
+                        if ui.button("PNG").clicked() {
+                            self.export_picture(false);
+                        }
+                        if ui
+                            .button("SVG")
+                            .on_hover_text("Scalable picture with the field image embedded")
+                            .clicked()
+                        {
+                            self.export_picture(true);
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        egui::ComboBox::from_id_salt("report_format")
+                            .selected_text(format!("{:?}", self.report_format))
+                            .show_ui(ui, |ui| {
+                                for format in [ReportFormat::Markdown, ReportFormat::Html] {
+                                    ui.selectable_value(
+                                        &mut self.report_format,
+                                        format,
+                                        format!("{format:?}"),
+                                    );
+                                }
+                            });
+                        if ui
+                            .button("Report")
+                            .on_hover_text(
+                                "Notebook report with the field, waypoints, commands and times",
+                            )
+                            .clicked()
+                        {
+                            self.export_report();
+                        }
+                    });
+                    ui.label("Shortcuts");
+                    ui.separator();
+                    self.shortcuts_ui(ui);
                     ui.label("Save Data");
                     ui.separator();
                     ui.horizontal(|ui| {
                         if ui.button("Save").clicked() {
-                            self.save_data = serde_json::to_string(&self.get_save()).unwrap();
+                            self.save_data = self.project().to_json();
                         }
                         if ui.button("Load").clicked() {
-                            if let Ok(data) = serde_json::from_str::<Vec<Point>>(&self.save_data) {
-                                self.load_save(&data);
+                            if let Ok(project) = Project::from_json(&self.save_data) {
+                                self.name = project.name;
+                                self.path = project.path;
+                                self.robot = project.robot;
+                                self.options = project.options;
                                 self.generate();
                             }
                         }
+                        ui.separator();
+                        if ui
+                            .button("Export LemLib")
+                            .on_hover_text("Write the path as a LemLib path file for path.jerryio")
+                            .clicked()
+                        {
+                            self.save_data = lemlib::export(
+                                &self.path,
+                                &self.robot,
+                                &self.options.pursuit,
+                                self.field.width,
+                            );
+                        }
+                        if ui
+                            .button("Import LemLib")
+                            .on_hover_text("Read a LemLib or path.jerryio path file")
+                            .clicked()
+                        {
+                            self.import_lemlib(&self.save_data.clone());
+                        }
                     });
+                    if let Some(message) = &self.message {
+                        ui.colored_label(ui.visuals().warn_fg_color, message);
+                    }
                     ui.add(
                         TextEdit::multiline(&mut self.save_data)
                             .font(egui::FontId::monospace(12.0))
@@ -365,21 +1848,66 @@ impl eframe::App for PathyApp {
             let (rect, resp) = ui.allocate_exact_size(
                 Vec2 {
                     x: self.scale as f32,
-                    y: self.scale as f32,
+                    y: self.scale as f32 * self.field.height / self.field.width,
                 },
                 egui::Sense::click_and_drag(),
             );
-            // Check for dropped image
-            if self.background == Background::Custom {
-                ctx.input(|i| {
-                    if let Some(file) = i.raw.dropped_files.last() {
-                        self.uploaded = file.clone().bytes;
-                        self.load_field_overlay();
-                    }
-                });
+            self.field_rect = rect;
+            // Check for dropped path files, telemetry logs and images
+            let dropped = ctx.input(|i| i.raw.dropped_files.last().cloned());
+            if let Some((name, bytes)) = dropped.as_ref().and_then(read_dropped) {
+                // LemLib and path.jerryio path files
+                if has_extension(&name, "txt") {
+                    self.import_lemlib(&String::from_utf8_lossy(&bytes));
+                    return;
+                }
+                if has_extension(&name, "csv") || has_extension(&name, "json") {
+                    self.import_log(&name, &String::from_utf8_lossy(&bytes));
+                    return;
+                }
+                self.add_image(&name, &bytes);
+            }
+            if self.cursor_mode != CursorMode::Calibrate {
+                self.calibrating = None;
             }
-            match &self.overlay {
-                Some(image) => {
+            let ratio = self.ratio();
+            let transform = self
+                .field
+                .image_transform()
+                .filter(|_| self.calibrating.is_none());
+            match (&self.overlay, transform) {
+                (Some(image), Some(transform)) => {
+                    // Warp the image with a fine mesh, so perspective looks straight
+                    const STEPS: u32 = 32;
+                    let size = egui::vec2(image.size()[0] as f32, image.size()[1] as f32);
+                    let mut mesh = egui::Mesh::with_texture(image.texture_id(ctx));
+                    for j in 0..=STEPS {
+                        for i in 0..=STEPS {
+                            let t = egui::vec2(i as f32, j as f32) / STEPS as f32;
+                            let field = pos2(t.x * self.field.width, t.y * self.field.height);
+                            let uv = (transform.apply(field).to_vec2() / size).to_pos2();
+                            // Fade out anything the picture doesn't cover
+                            let inside = (0.0..=1.0).contains(&uv.x) && (0.0..=1.0).contains(&uv.y);
+                            mesh.vertices.push(egui::epaint::Vertex {
+                                pos: rect.min + field.to_vec2() * ratio,
+                                uv,
+                                color: if inside {
+                                    Color32::WHITE
+                                } else {
+                                    Color32::TRANSPARENT
+                                },
+                            });
+                            if i > 0 && j > 0 {
+                                let corner = j * (STEPS + 1) + i;
+                                let (left, up) = (corner - 1, corner - STEPS - 1);
+                                mesh.add_triangle(up - 1, up, corner);
+                                mesh.add_triangle(up - 1, corner, left);
+                            }
+                        }
+                    }
+                    ui.painter().add(mesh);
+                }
+                (Some(image), None) => {
                     ui.painter().image(
                         image.texture_id(ctx),
                         rect,
@@ -400,63 +1928,177 @@ impl eframe::App for PathyApp {
                 }
             }
 
+            // Obstacles
+            for obstacle in &self.field.obstacles {
+                let stroke = Stroke::new(2.0, Color32::RED);
+                let fill = Color32::from_rgba_unmultiplied(255, 0, 0, 40);
+                match *obstacle {
+                    Obstacle::Rect {
+                        x,
+                        y,
+                        width,
+                        height,
+                    } => {
+                        let min = rect.min + egui::vec2(x, y) * ratio;
+                        let area =
+                            egui::Rect::from_min_size(min, egui::vec2(width, height) * ratio);
+                        ui.painter().rect(area, 0.0, fill, stroke);
+                    }
+                    Obstacle::Circle { x, y, radius } => {
+                        let center = rect.min + egui::vec2(x, y) * ratio;
+                        ui.painter().circle(center, radius * ratio, fill, stroke);
+                    }
+                }
+            }
+
+            // Logged runs, under the planned path
+            for (i, run) in self.runs.iter().enumerate() {
+                let line: Vec<Pos2> = run
+                    .poses
+                    .iter()
+                    .map(|pose| rect.min + pose.pos.to_vec2() * ratio)
+                    .collect();
+                ui.painter()
+                    .add(egui::Shape::line(line, Stroke::new(2.0, run_color(i))));
+            }
+
+            // The compared version, as a ghost under the path
+            if let Some(version) = &self.compare {
+                let ghost = Color32::from_rgba_unmultiplied(220, 220, 220, 110);
+                let old = version.project.path.points();
+                for (_, segment) in segments(&waypoints(old)) {
+                    let segment = segment.transform(ratio, rect.min.to_vec2());
+                    let steps = (segment.length() / 4.0).ceil().max(1.0) as usize;
+                    let line = (0..=steps)
+                        .map(|i| segment.sample(i as f32 / steps as f32))
+                        .collect();
+                    ui.painter()
+                        .add(egui::Shape::line(line, Stroke::new(2.0, ghost)));
+                }
+                for point in old {
+                    ui.painter().circle_stroke(
+                        point.screen(ratio, rect.min),
+                        5.0,
+                        Stroke::new(2.0, ghost),
+                    );
+                }
+            }
+
             /* POINT RENDERING + HOVER DETECTION */
-            // Render curve points
+            // Render path segments
             let mut min_dis = f32::MAX;
             let mut closest: Option<Pos2> = None;
             let mut closest_idx: usize = 0;
-            if self.points.len() >= 2 {
-                for idx in 0..self.points.len() - 1 {
-                    let a = self.points[idx].borrow_mut();
-                    let mut b = self.points[idx + 1].borrow_mut();
-                    let steps =
-                        f32::floor(Pos2::from(b.clone()).distance(Pos2::from(a.clone()))) as usize;
-                    // evaluate each pair
-                    let draw_steps = if !b.animated {
+            let path = path_waypoints(&self.path);
+            // Find the hovered point, ensuring only 1 point gets selected.
+            // Branch points have no index on the main path.
+            let hovered: Option<(usize, Uuid)> = resp.hover_pos().and_then(|pos| {
+                self.path
+                    .all_points()
+                    .find(|p| p.contains(ratio, rect.min, pos))
+                    .map(|p| (self.path.index_of(p.id).unwrap_or(usize::MAX), p.id))
+            });
+            let active = |id: Uuid| {
+                hovered.is_some_and(|(_, hovered)| hovered == id) || self.selected == Some(id)
+            };
+            for (idx, segment) in segments(&path) {
+                let segment = segment.transform(ratio, rect.min.to_vec2());
+                let a = &self.path.points()[idx];
+                let b = &self.path.points()[idx + 1];
+                let a_pos = a.screen(ratio, rect.min);
+                let b_pos = b.screen(ratio, rect.min);
+                let steps = f32::floor(segment.length()) as usize;
+                // evaluate each segment, animating straight lines as they are created
+                let draw_steps =
+                    if !self.animated.contains(&b.id) && matches!(segment, Segment::Line { .. }) {
                         ctx.animate_value_with_time(ui.make_persistent_id(b.id), steps as f32, 0.15)
                             as usize
                     } else {
                         steps
                     };
-                    // Lock once animation completed
-                    // So step size changes don't animate
-                    if draw_steps >= steps {
-                        b.animated = true;
-                    }
-                    for i in 1..draw_steps {
-                        let point: Pos2 = interpolate(
-                            &a.screen_clone(self.scale as f32 / self.size, rect.min),
-                            &b.screen_clone(self.scale as f32 / self.size, rect.min),
-                            i as f32 / steps as f32,
-                        )
-                        .into();
-                        ui.painter().circle_filled(point, 2.0, Color32::YELLOW);
-                        // If insert mode, find closest point
-                        if self.cursor_mode == CursorMode::Insert {
-                            if let Some(pos) = resp.hover_pos() {
-                                let dist = point.distance_sq(pos);
-                                if dist < min_dis {
-                                    min_dis = dist;
-                                    closest = Some(point);
-                                    closest_idx = idx;
-                                }
+                // Lock once animation completed
+                // So step size changes don't animate
+                if draw_steps >= steps {
+                    self.animated.insert(b.id);
+                }
+                for i in 1..draw_steps {
+                    let point = segment.sample(i as f32 / steps as f32);
+                    // Keep clear of the control points themselves
+                    if point.distance(a_pos) < Point::get_radius(active(a.id))
+                        || point.distance(b_pos) < Point::get_radius(active(b.id))
+                    {
+                        continue;
+                    }
+                    ui.painter().circle_filled(point, 2.0, Color32::YELLOW);
+                    // If insert mode, find closest point
+                    if self.cursor_mode == CursorMode::Insert {
+                        if let Some(pos) = resp.hover_pos() {
+                            let dist = point.distance_sq(pos);
+                            if dist < min_dis {
+                                min_dis = dist;
+                                closest = Some(point);
+                                closest_idx = idx;
                             }
                         }
                     }
                 }
             }
 
-            // Draw points & check for selection
-            let mut selected: Option<Rc<RefCell<Point>>> = None; // references currently selected point
-            let mut idx: Option<usize> = None;
-            for (i, point) in &mut self.points.iter_mut().enumerate() {
-                let hovered = point.borrow_mut().draw(
+            // Branches, dashed from where they leave to where they rejoin
+            for branch in self.path.branches() {
+                let Some(from) = self.path.get(branch.from) else {
+                    continue;
+                };
+                let rejoin = branch.rejoin.and_then(|id| self.path.get(id));
+                let route: Vec<Pos2> = std::iter::once(from)
+                    .chain(&branch.points)
+                    .chain(rejoin)
+                    .map(|p| p.screen(ratio, rect.min))
+                    .collect();
+                ui.painter().extend(egui::Shape::dashed_line(
+                    &route,
+                    Stroke::new(2.0, BRANCH_COLOR),
+                    6.0,
+                    4.0,
+                ));
+                let start = from.screen(ratio, rect.min);
+                let toward = route
+                    .get(1)
+                    .map_or(Vec2::X, |next| (*next - start).normalized());
+                ui.painter().text(
+                    start + toward * 24.0,
+                    egui::Align2::CENTER_BOTTOM,
+                    format!("if {}", branch.condition.trim()),
+                    egui::FontId::proportional(13.0),
+                    BRANCH_COLOR,
+                );
+                for point in &branch.points {
+                    point.draw(
+                        ui,
+                        ctx,
+                        ratio,
+                        rect.min,
+                        if self.cursor_mode == CursorMode::Trim {
+                            &CursorMode::Delete
+                        } else {
+                            &self.cursor_mode
+                        },
+                        hovered.is_some_and(|(_, id)| id == point.id),
+                        self.selected == Some(point.id),
+                    );
+                }
+            }
+
+            // Draw points
+            for (i, point) in self.path.points().iter().enumerate() {
+                let is_hovered = hovered.is_some_and(|(_, id)| id == point.id);
+                point.draw(
                     ui,
                     ctx,
-                    self.scale as f32 / self.size,
+                    ratio,
                     rect.min,
                     if self.cursor_mode == CursorMode::Trim {
-                        if idx.is_some() {
+                        if hovered.is_some_and(|(idx, _)| i > idx) {
                             &CursorMode::Trim
                         } else {
                             &CursorMode::Delete
@@ -464,44 +2106,97 @@ impl eframe::App for PathyApp {
                     } else {
                         &self.cursor_mode
                     },
-                    if selected.is_none() {
-                        resp.hover_pos()
-                    } else {
-                        None
-                    }, // ensure only 1 point gets selected
+                    is_hovered,
+                    self.selected == Some(point.id),
                 );
-                if hovered {
-                    idx = Some(i);
-                    selected = Some(point.clone());
-                }
             }
-            if let Some(point) = &selected {
-                self.inspecting = Some(point.clone());
+            if let Some((_, id)) = hovered {
+                self.inspecting = Some(id);
             }
 
+            /* PLAYBACK */
+            if let Some(distance) = self.playback {
+                let samples = sample(&path, &self.robot, &self.options.pursuit);
+                let to_screen = |pos: Pos2| rect.min + pos.to_vec2() * ratio;
+                if let Some(idx) = samples.iter().position(|p| p.distance >= distance) {
+                    let robot = samples[idx];
+                    if self.playing {
+                        // Never stall on the zero velocity at the end of the path
+                        let velocity = robot.velocity.max(self.robot.max_velocity() * 0.1);
+                        let total = samples.last().unwrap().distance;
+                        let next = distance + velocity * ctx.input(|i| i.stable_dt);
+                        self.playback = Some(next.min(total));
+                        self.playing = next < total;
+                        ctx.request_repaint();
+                    }
+                    let pos = to_screen(robot.pos);
+                    // Draw the robot's footprint facing along the path
+                    let next = samples[(idx + 1).min(samples.len() - 1)].pos;
+                    let prev = samples[idx.saturating_sub(1)].pos;
+                    let forward = (next - prev).normalized() * self.robot.length / 2.0 * ratio;
+                    let side = forward.rot90().normalized() * self.robot.width / 2.0 * ratio;
+                    ui.painter().add(egui::Shape::closed_line(
+                        vec![
+                            pos + forward + side,
+                            pos + forward - side,
+                            pos - forward - side,
+                            pos - forward + side,
+                        ],
+                        Stroke::new(2.0, Color32::LIGHT_BLUE),
+                    ));
+                    ui.painter()
+                        .line_segment([pos, pos + forward], Stroke::new(2.0, Color32::LIGHT_BLUE));
+                    if matches!(
+                        self.options.backend,
+                        Backend::PursuitArray | Backend::PursuitFile
+                    ) {
+                        let radius = self.options.pursuit.lookahead;
+                        ui.painter().circle_stroke(
+                            pos,
+                            radius * ratio,
+                            Stroke::new(1.5, Color32::LIGHT_BLUE),
+                        );
+                        if let Some(target) = lookahead(&samples, idx, radius) {
+                            let target = to_screen(target);
+                            ui.painter()
+                                .line_segment([pos, target], Stroke::new(1.5, Color32::LIGHT_BLUE));
+                            ui.painter().circle_filled(target, 4.0, Color32::LIGHT_BLUE);
+                        }
+                    }
+                }
+            }
+
+            // Measurements snap to waypoints
+            let measured = resp.hover_pos().map(|pos| {
+                hovered
+                    .and_then(|(_, id)| self.path.get(id))
+                    .map_or((pos - rect.min) / ratio, |p| egui::vec2(p.x, p.y))
+                    .to_pos2()
+            });
+
             /* INPUT HANDLERS */
             if ctx.input(|i| i.pointer.button_down(egui::PointerButton::Primary))
-                && !matches!(self.cursor_mode, CursorMode::Delete | CursorMode::Trim)
+                && !matches!(
+                    self.cursor_mode,
+                    CursorMode::Delete
+                        | CursorMode::Trim
+                        | CursorMode::Measure
+                        | CursorMode::Calibrate
+                )
             {
                 // Lock selection in case of drag
                 if self.selected.is_none() {
-                    if let Some(point) = &selected {
-                        point.borrow_mut().locked = true;
-                        self.selected = Some(point.clone());
-                    }
+                    self.selected = hovered.map(|(_, id)| id);
                 }
             }
             if ctx.input(|i| i.pointer.button_released(egui::PointerButton::Primary)) {
                 // Unlock any selection
-                if let Some(point) = &self.selected {
-                    point.borrow_mut().locked = false;
-                    self.selected = None;
-                }
+                self.selected = None;
             }
             if resp.clicked() {
                 match &self.cursor_mode {
                     CursorMode::Create => {
-                        if selected.is_some() {
+                        if hovered.is_some() {
                             return;
                         }
                         if let Some(pos) = resp.hover_pos() {
@@ -514,59 +2209,85 @@ impl eframe::App for PathyApp {
                                 return;
                             }
                             // Calculate points relative to field
-                            let x = (pos.x - rect.min.x) * (self.size / self.scale as f32);
-                            let y = (pos.y - rect.min.y) * (self.size / self.scale as f32);
-                            self.points.push(Rc::new(RefCell::new(Point::new(x, y))));
-                            if !self.points.is_empty() {
-                                // setup initial animation value
-                                ctx.animate_value_with_time(
-                                    ui.make_persistent_id(self.points.last().unwrap().borrow().id),
-                                    0.0,
-                                    0.5,
-                                );
+                            let x = (pos.x - rect.min.x) * (self.field.width / self.scale as f32);
+                            let y = (pos.y - rect.min.y) * (self.field.width / self.scale as f32);
+                            let point = Point::new(x, y);
+                            let id = point.id;
+                            let branch = self
+                                .branching
+                                .and_then(|i| self.path.branches_mut().get_mut(i));
+                            match branch {
+                                Some(branch) => branch.points.push(point),
+                                None => {
+                                    self.path.push(point);
+                                }
                             }
+                            // setup initial animation value
+                            ctx.animate_value_with_time(ui.make_persistent_id(id), 0.0, 0.5);
                             self.generate();
                         }
                     }
                     CursorMode::Delete => {
-                        if let Some(i) = idx {
-                            self.points.remove(i);
+                        if let Some((_, id)) = hovered {
+                            self.path.remove(id);
                             self.generate();
                         }
                     }
                     CursorMode::Trim => {
-                        if let Some(i) = idx {
-                            self.points.truncate(i);
+                        if let Some((_, id)) = hovered {
+                            self.path.trim(id);
                             self.generate();
                         }
                     }
                     CursorMode::Insert => {
                         if let Some(pos) = closest {
-                            let x = (pos.x - rect.min.x) * (self.size / self.scale as f32);
-                            let y = (pos.y - rect.min.y) * (self.size / self.scale as f32);
+                            let x = (pos.x - rect.min.x) * (self.field.width / self.scale as f32);
+                            let y = (pos.y - rect.min.y) * (self.field.width / self.scale as f32);
                             // Calculate future x and ys
-                            self.points
-                                .insert(closest_idx + 1, Rc::new(RefCell::new(Point::new(x, y))));
+                            self.path.insert(closest_idx + 1, Point::new(x, y));
                             self.generate();
                         }
                     }
+                    CursorMode::Measure => {
+                        if let Some(pos) = measured {
+                            // A third click starts over
+                            self.measure = match self.measure {
+                                Some((start, None)) => Some((start, Some(pos))),
+                                _ => Some((pos, None)),
+                            };
+                        }
+                    }
+                    CursorMode::Calibrate => {
+                        let size = self.overlay.as_ref().map(|image| image.size());
+                        if let (Some((landmarks, clicked)), Some(pos), Some([w, h])) =
+                            (&mut self.calibrating, resp.hover_pos(), size)
+                        {
+                            // The image is stretched over the field while its landmarks are clicked
+                            let uv = (pos - rect.min) / rect.size();
+                            let landmark = &mut landmarks[*clicked];
+                            landmark.image_x = uv.x * w as f32;
+                            landmark.image_y = uv.y * h as f32;
+                            *clicked += 1;
+                            if *clicked == landmarks.len() {
+                                self.field.calibration = std::mem::take(landmarks);
+                                self.calibrating = None;
+                                self.cursor_mode = CursorMode::Default;
+                            }
+                        }
+                    }
                     _ => {}
                 }
             }
 
             if resp.dragged() && resp.contains_pointer() {
-                let mut changed = false;
-                if let Some(point) = &self.selected {
-                    if let Some(pos) = ctx.pointer_interact_pos() {
-                        if let Ok(mut p) = point.try_borrow_mut() {
-                            p.x = (pos.x - rect.min.x) * (self.size / self.scale as f32);
-                            p.y = (pos.y - rect.min.y) * (self.size / self.scale as f32);
-                            changed = true;
-                        }
-                    }
-                }
-                if changed {
-                    self.generate(); // afterwards to please borrow checker
+                let field = ctx
+                    .pointer_interact_pos()
+                    .map(|pos| (pos - rect.min) * (self.field.width / self.scale as f32));
+                let point = self.selected.and_then(|id| self.path.get_mut(id));
+                if let (Some(point), Some(field)) = (point, field) {
+                    point.x = field.x;
+                    point.y = field.y;
+                    self.generate();
                 }
             }
 
@@ -574,7 +2295,7 @@ impl eframe::App for PathyApp {
             match &self.cursor_mode {
                 CursorMode::Create => {
                     // Display circle under pointer
-                    if self.selected.is_some() || selected.is_some() {
+                    if self.selected.is_some() || hovered.is_some() {
                         return;
                     }
                     if let Some(pos) = resp.hover_pos() {
@@ -589,15 +2310,283 @@ impl eframe::App for PathyApp {
                             .circle_stroke(pos, 5.0, Stroke::new(2.0, Color32::YELLOW));
                     }
                 }
+                CursorMode::Measure => {
+                    // Draw the ruler from the start to the end, or to the cursor until then
+                    let Some((start, end)) = self.measure else {
+                        return;
+                    };
+                    let Some(end) = end.or(measured) else {
+                        return;
+                    };
+                    let to_screen = |pos: Pos2| rect.min + pos.to_vec2() * ratio;
+                    let stroke = Stroke::new(2.0, Color32::ORANGE);
+                    ui.painter()
+                        .line_segment([to_screen(start), to_screen(end)], stroke);
+                    ui.painter()
+                        .circle_filled(to_screen(start), 3.0, Color32::ORANGE);
+                    ui.painter()
+                        .circle_filled(to_screen(end), 3.0, Color32::ORANGE);
+                    let unit = self.options.drive_unit;
+                    let text = format!(
+                        "{:.2} {}, {:.1}°",
+                        unit.convert(start.distance(end), &self.robot),
+                        unit.suffix(),
+                        self.options
+                            .frame
+                            .heading((end - start).angle().to_degrees())
+                    );
+                    ui.painter().text(
+                        to_screen(end) + egui::vec2(8.0, -8.0),
+                        egui::Align2::LEFT_BOTTOM,
+                        text,
+                        egui::FontId::proportional(14.0),
+                        Color32::ORANGE,
+                    );
+                }
+                CursorMode::Calibrate => {
+                    let (Some((landmarks, clicked)), Some(image)) =
+                        (&self.calibrating, &self.overlay)
+                    else {
+                        return;
+                    };
+                    let size = egui::vec2(image.size()[0] as f32, image.size()[1] as f32);
+                    for landmark in &landmarks[..*clicked] {
+                        let uv = egui::vec2(landmark.image_x, landmark.image_y) / size;
+                        ui.painter().circle_stroke(
+                            rect.min + uv * rect.size(),
+                            6.0,
+                            Stroke::new(2.0, Color32::GREEN),
+                        );
+                    }
+                    // Ask for the next landmark beside the cursor
+                    if let (Some(pos), Some(next)) = (resp.hover_pos(), landmarks.get(*clicked)) {
+                        ui.painter()
+                            .circle_stroke(pos, 6.0, Stroke::new(2.0, Color32::GREEN));
+                        ui.painter().text(
+                            pos + egui::vec2(10.0, -10.0),
+                            egui::Align2::LEFT_BOTTOM,
+                            format!(
+                                "Click landmark {} of {} at ({:.1}, {:.1})",
+                                clicked + 1,
+                                landmarks.len(),
+                                next.x,
+                                next.y
+                            ),
+                            egui::FontId::proportional(14.0),
+                            Color32::GREEN,
+                        );
+                    }
+                }
                 _ => {}
             }
 
             ui.with_layout(egui::Layout::bottom_up(egui::Align::LEFT), |ui| {
-                if self.background == Background::Custom && self.overlay.is_none() {
+                if self.overlay.is_none() {
                     ui.label("Drag an drop an image to set the field background!");
                 }
+                let blocked = self
+                    .path
+                    .points()
+                    .iter()
+                    .filter(|p| !self.field.is_clear(pos2(p.x, p.y)))
+                    .count();
+                if blocked > 0 {
+                    ui.colored_label(
+                        ui.visuals().warn_fg_color,
+                        format!("{blocked} points are off the field or inside obstacles"),
+                    );
+                }
                 egui::warn_if_debug_build(ui);
             });
         });
+
+        self.palette_ui(ctx);
+        self.track_history(ctx);
+    }
+}
+
+/// A short summary of a waypoint's position and settings, in the field's frame.
+fn describe_point(point: &Point, frame: &Frame) -> String {
+    let pos = frame.to_frame(pos2(point.x, point.y));
+    let mut text = format!("({:.1}, {:.1})", pos.x, pos.y);
+    if point.radius > 0.0 {
+        text += &format!(" arc {:.1}", point.radius);
+    }
+    if let Some(heading) = point.heading {
+        text += &format!(" facing {:.0}°", frame.heading(heading));
+    }
+    if point.reverse {
+        text += " reverse";
+    }
+    if let Some(speed) = point.speed {
+        text += &format!(" {speed:.0}%");
+    }
+    if !point.markers.is_empty() {
+        text += &format!(" {} markers", point.markers.len());
+    }
+    text
+}
+
+/// Reads the name and contents of a dropped file.
+/// Native drops only have a path, so the name comes from it when missing.
+fn read_dropped(file: &egui::DroppedFile) -> Option<(String, Vec<u8>)> {
+    let bytes = match (&file.bytes, &file.path) {
+        (Some(bytes), _) => bytes.to_vec(),
+        (None, Some(path)) => std::fs::read(path).ok()?,
+        _ => return None,
+    };
+    let name = match &file.path {
+        Some(path) if file.name.is_empty() => path
+            .file_name()
+            .map_or(String::new(), |n| n.to_string_lossy().into_owned()),
+        _ => file.name.clone(),
+    };
+    Some((name, bytes))
+}
+
+/// Whether a file name ends in an extension, ignoring case.
+fn has_extension(name: &str, extension: &str) -> bool {
+    std::path::Path::new(name)
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case(extension))
+}
+
+/// Color branches are drawn in.
+const BRANCH_COLOR: Color32 = Color32::from_rgb(80, 220, 200);
+
+/// Color a logged run is drawn in, telling runs apart.
+fn run_color(i: usize) -> Color32 {
+    const COLORS: [Color32; 4] = [
+        Color32::from_rgb(0, 200, 255),
+        Color32::from_rgb(255, 80, 200),
+        Color32::from_rgb(120, 255, 120),
+        Color32::from_rgb(255, 150, 50),
+    ];
+    COLORS[i % COLORS.len()]
+}
+
+/// Shows an optional number with a checkbox to set or clear it,
+/// returning the combined response.
+fn optional_value(
+    ui: &mut egui::Ui,
+    value: &mut Option<f32>,
+    default: f32,
+    range: std::ops::RangeInclusive<f32>,
+    suffix: &str,
+) -> egui::Response {
+    ui.horizontal(|ui| {
+        let mut set = value.is_some();
+        let mut response = ui.checkbox(&mut set, "");
+        if response.changed() {
+            *value = set.then_some(default);
+        }
+        if let Some(value) = value {
+            response |= ui.add(egui::DragValue::new(value).range(range).suffix(suffix));
+        }
+        response
+    })
+    .inner
+}
+
+/// Shows one coordinate of a point in a field's frame for editing.
+fn coordinate_ui(ui: &mut egui::Ui, point: &mut Point, frame: &Frame, y: bool) -> egui::Response {
+    let corners = [
+        frame.to_frame(pos2(0.0, 0.0)),
+        frame.to_frame(pos2(frame.width, frame.height)),
+    ];
+    let mut pos = frame.to_frame(pos2(point.x, point.y));
+    let (value, min, max, prefix) = if y {
+        (&mut pos.y, corners[0].y, corners[1].y, "y: ")
+    } else {
+        (&mut pos.x, corners[0].x, corners[1].x, "x: ")
+    };
+    let response = ui.add(
+        egui::DragValue::new(value)
+            .range(min.min(max)..=min.max(max))
+            .speed(0.1)
+            .prefix(prefix)
+            .suffix(" in"),
+    );
+    if response.changed() {
+        let pos = frame.from_frame(pos);
+        point.x = pos.x;
+        point.y = pos.y;
+    }
+    response
+}
+
+/// Shows an optional heading in a field's frame for editing.
+fn heading_ui(ui: &mut egui::Ui, heading: &mut Option<f32>, frame: &Frame) -> egui::Response {
+    let mut shown = heading.map(|h| frame.heading(h));
+    let response = optional_value(ui, &mut shown, 0.0, -180.0..=180.0, "°");
+    if response.changed() {
+        *heading = shown.map(|h| frame.heading(h));
+    }
+    response
+}
+
+/// Shows every property of a point for editing, returning whether any changed.
+fn point_ui(ui: &mut egui::Ui, point: &mut Point, frame: &Frame) -> bool {
+    let mut updated = false;
+    egui::Grid::new("point").show(ui, |ui| {
+        ui.label("Position: ");
+        ui.horizontal(|ui| {
+            updated |= coordinate_ui(ui, point, frame, false).changed();
+            updated |= coordinate_ui(ui, point, frame, true).changed();
+        });
+        ui.end_row();
+        ui.label("Arc Radius: ");
+        updated |= ui
+            .add(
+                egui::DragValue::new(&mut point.radius)
+                    .range(0.0..=f32::INFINITY)
+                    .speed(0.1)
+                    .suffix(" in"),
+            )
+            .on_hover_text("Replace the turn at this point with an arc (0 to turn in place)")
+            .changed();
+        ui.end_row();
+        ui.label("Heading: ");
+        updated |= heading_ui(ui, &mut point.heading, frame)
+            .on_hover_text("Turn to face this heading on reaching the point")
+            .changed();
+        ui.end_row();
+        ui.label("Reverse: ");
+        updated |= ui
+            .checkbox(&mut point.reverse, "")
+            .on_hover_text("Drive backwards into this point")
+            .changed();
+        ui.end_row();
+        ui.label("Speed: ");
+        updated |= optional_value(ui, &mut point.speed, 100.0, 1.0..=100.0, "%")
+            .on_hover_text("Limit the speed on the way into this point")
+            .changed();
+        ui.end_row();
+    });
+    ui.label("Markers: ")
+        .on_hover_text("Code run on reaching this point");
+    let mut removed = None;
+    for (i, marker) in point.markers.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            if ui
+                .small_button("x")
+                .on_hover_text("Remove marker")
+                .clicked()
+            {
+                removed = Some(i);
+            }
+            updated |= ui
+                .add(TextEdit::singleline(marker).font(egui::FontId::monospace(12.0)))
+                .changed();
+        });
+    }
+    if let Some(i) = removed {
+        point.markers.remove(i);
+        updated = true;
+    }
+    if ui.button("Add Marker").clicked() {
+        point.markers.push(String::new());
+        updated = true;
     }
+    updated
 }
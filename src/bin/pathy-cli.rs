@@ -0,0 +1,9 @@
+#![warn(clippy::all, rust_2018_idioms)]
+
+// Generates path code from the command line, without opening the app.
+fn main() {
+    if let Err(err) = pathy::cli::run(std::env::args().skip(1)) {
+        eprintln!("{err}");
+        std::process::exit(1);
+    }
+}
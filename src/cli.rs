@@ -0,0 +1,86 @@
+use std::io::{Read, Write};
+
+use crate::generate::Backend;
+use crate::project::Project;
+
+const USAGE: &str = "\
+Usage: pathy-cli <PROJECT> [options]
+
+Generates path code from a Pathy project file, as saved from the Save Data panel.
+Use - as the project to read it from stdin.
+
+Options:
+  -b, --backend <BACKEND>  Output to generate, overriding the project's setting
+                           [chassis, pursuit-array, pursuit-file, trajectory-csv, trajectory-header]
+  -o, --output <FILE>      Write to a file instead of stdout
+  -h, --help               Print this help
+";
+
+/// Parses a backend name given on the command line.
+fn parse_backend(name: &str) -> Result<Backend, String> {
+    match name {
+        "chassis" => Ok(Backend::Chassis),
+        "pursuit-array" => Ok(Backend::PursuitArray),
+        "pursuit-file" => Ok(Backend::PursuitFile),
+        "trajectory-csv" => Ok(Backend::TrajectoryCsv),
+        "trajectory-header" => Ok(Backend::TrajectoryHeader),
+        _ => Err(format!("Unknown backend `{name}`\n\n{USAGE}")),
+    }
+}
+
+/// Runs the command line generator with the given arguments, excluding the program name.
+pub fn run(args: impl IntoIterator<Item = String>) -> Result<(), String> {
+    let mut project_path: Option<String> = None;
+    let mut backend: Option<Backend> = None;
+    let mut output: Option<String> = None;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                print!("{USAGE}");
+                return Ok(());
+            }
+            "-b" | "--backend" => {
+                let name = args.next().ok_or("Missing value for --backend")?;
+                backend = Some(parse_backend(&name)?);
+            }
+            "-o" | "--output" => {
+                output = Some(args.next().ok_or("Missing value for --output")?);
+            }
+            _ if project_path.is_none() && (arg == "-" || !arg.starts_with('-')) => {
+                project_path = Some(arg);
+            }
+            _ => return Err(format!("Unexpected argument `{arg}`\n\n{USAGE}")),
+        }
+    }
+    let project_path = project_path.ok_or(USAGE)?;
+
+    // Read and parse the project
+    let json = if project_path == "-" {
+        let mut json = String::new();
+        std::io::stdin()
+            .read_to_string(&mut json)
+            .map_err(|e| format!("Failed to read stdin: {e}"))?;
+        json
+    } else {
+        std::fs::read_to_string(&project_path)
+            .map_err(|e| format!("Failed to read {project_path}: {e}"))?
+    };
+    let mut project =
+        Project::from_json(&json).map_err(|e| format!("Invalid project {project_path}: {e}"))?;
+    if let Some(backend) = backend {
+        project.options.backend = backend;
+    }
+
+    // Generate and write out the code
+    let code = project.generate();
+    match output {
+        Some(path) => {
+            std::fs::write(&path, code).map_err(|e| format!("Failed to write {path}: {e}"))
+        }
+        None => std::io::stdout()
+            .write_all(code.as_bytes())
+            .map_err(|e| format!("Failed to write to stdout: {e}")),
+    }
+}
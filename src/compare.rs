@@ -0,0 +1,168 @@
+use std::collections::HashSet;
+
+use crate::generate::{commands, format_commands, GenerateOptions};
+use crate::point::Point;
+use crate::robot::Robot;
+
+/// How one waypoint differs between two versions of a path.
+#[derive(Clone, Debug, PartialEq)]
+pub enum WaypointChange {
+    /// Only in the new version, at this index
+    Added(usize, Point),
+    /// Only in the old version, at this index
+    Removed(usize, Point),
+    /// In both versions with different settings, each with its index
+    Changed {
+        before: (usize, Point),
+        after: (usize, Point),
+    },
+}
+
+/// How one line differs between two versions of generated code.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LineChange {
+    Same(String),
+    Added(String),
+    Removed(String),
+}
+
+/// Lists the waypoints added, removed or changed from `old` to `new`,
+/// in the new path's order followed by the removed ones.
+///
+/// Waypoints are matched by id, so moving or editing a point is a change rather than
+/// a removal and an addition. Paths sharing no ids, like ones saved separately,
+/// are matched by their position in the path instead.
+pub fn waypoint_changes(old: &[Point], new: &[Point]) -> Vec<WaypointChange> {
+    let old_ids: HashSet<_> = old.iter().map(|p| p.id).collect();
+    let by_id = new.iter().any(|p| old_ids.contains(&p.id));
+    let find = |i: usize, point: &Point, other: &[Point]| {
+        if by_id {
+            other.iter().position(|p| p.id == point.id)
+        } else {
+            (i < other.len()).then_some(i)
+        }
+    };
+
+    let mut changes = Vec::new();
+    for (i, point) in new.iter().enumerate() {
+        match find(i, point, old) {
+            None => changes.push(WaypointChange::Added(i, point.clone())),
+            Some(j) if !same_settings(&old[j], point) => changes.push(WaypointChange::Changed {
+                before: (j, old[j].clone()),
+                after: (i, point.clone()),
+            }),
+            Some(_) => {}
+        }
+    }
+    for (j, point) in old.iter().enumerate() {
+        if find(j, point, new).is_none() {
+            changes.push(WaypointChange::Removed(j, point.clone()));
+        }
+    }
+    changes
+}
+
+/// Whether two points are the same apart from their ids.
+fn same_settings(a: &Point, b: &Point) -> bool {
+    Point {
+        id: b.id,
+        ..a.clone()
+    } == *b
+}
+
+/// Lines of chassis code for each command driving a path.
+pub fn command_lines(points: &[Point], robot: &Robot, options: &GenerateOptions) -> Vec<String> {
+    format_commands(points, &commands(points), robot, options)
+        .into_iter()
+        .map(|line| line.trim().to_string())
+        .collect()
+}
+
+/// Lines kept, added and removed from `old` to `new`, keeping as many lines as possible.
+pub fn line_changes(old: &[String], new: &[String]) -> Vec<LineChange> {
+    // Longest common subsequence of every pair of suffixes
+    let mut lengths = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if old[i] == new[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut changes = Vec::new();
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            changes.push(LineChange::Same(new[j].clone()));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            changes.push(LineChange::Removed(old[i].clone()));
+            i += 1;
+        } else {
+            changes.push(LineChange::Added(new[j].clone()));
+            j += 1;
+        }
+    }
+    changes.extend(old[i..].iter().cloned().map(LineChange::Removed));
+    changes.extend(new[j..].iter().cloned().map(LineChange::Added));
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn waypoints_are_matched_by_id() {
+        let old = vec![
+            Point::new(0.0, 0.0),
+            Point::new(24.0, 0.0),
+            Point::new(24.0, 24.0),
+        ];
+        let mut new = old.clone();
+        new[1].x = 30.0;
+        let removed = new.remove(2);
+        let added = Point::new(48.0, 48.0);
+        new.push(added.clone());
+
+        let changes = waypoint_changes(&old, &new);
+        assert_eq!(
+            changes,
+            vec![
+                WaypointChange::Changed {
+                    before: (1, old[1].clone()),
+                    after: (1, new[1].clone()),
+                },
+                WaypointChange::Added(2, added),
+                WaypointChange::Removed(2, removed),
+            ]
+        );
+
+        // Separately saved paths are matched in order
+        let copy: Vec<Point> = old.iter().map(|p| Point::new(p.x, p.y)).collect();
+        assert!(waypoint_changes(&old, &copy).is_empty());
+    }
+
+    #[test]
+    fn lines_keep_the_longest_common_run() {
+        let lines = |text: &str| -> Vec<String> { text.split(' ').map(String::from).collect() };
+        let changes = line_changes(&lines("drive turn drive"), &lines("drive arc drive"));
+        assert_eq!(
+            changes,
+            vec![
+                LineChange::Same("drive".into()),
+                LineChange::Removed("turn".into()),
+                LineChange::Added("arc".into()),
+                LineChange::Same("drive".into()),
+            ]
+        );
+        assert_eq!(
+            line_changes(&[], &lines("drive")),
+            vec![LineChange::Added("drive".into())]
+        );
+    }
+}
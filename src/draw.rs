@@ -0,0 +1,91 @@
+use crate::app::CursorMode;
+use crate::point::Point;
+use egui::{lerp, pos2, Color32, Context, Pos2, Stroke, Ui, Vec2};
+
+/// Drawing of points on the field, kept apart from the path model.
+impl Point {
+    const RADIUS: f32 = 5.0;
+    const HOVER_RADIUS: f32 = 8.0;
+    const MARKER_RADIUS: f32 = 2.5;
+    /// Gets the screen position
+    pub(crate) fn screen(&self, ratio: f32, origin: Pos2) -> Pos2 {
+        pos2(self.x * ratio + origin.x, self.y * ratio + origin.y)
+    }
+
+    /// Checks if the cursor is over the point.
+    pub(crate) fn contains(&self, ratio: f32, origin: Pos2, hover_pos: Pos2) -> bool {
+        hover_pos.distance_sq(self.screen(ratio, origin)) < Self::RADIUS * Self::RADIUS
+    }
+
+    /// Draws the point, handling animations and hover states.
+    ///
+    /// # Arguments
+    /// * `ui` - The egui ui.
+    /// * `ctx` - The egui context.
+    /// * `ratio` - The ratio of the screen size to the field size.
+    /// * `origin` - The origin of the field(top-left corner).
+    /// * `mode` - The current cursor mode. CursorMode::Trim should only be supplied to points which will be deleted.
+    /// * `hovered` - Whether the cursor is over the point.
+    /// * `locked` - Whether the point is being dragged.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn draw(
+        &self,
+        ui: &mut Ui,
+        ctx: &Context,
+        ratio: f32,
+        origin: Pos2,
+        mode: &CursorMode,
+        hovered: bool,
+        locked: bool,
+    ) {
+        // Generate an id to keep track of point animations
+        let id = ui.make_persistent_id(self.id);
+
+        let active = hovered || locked;
+
+        // Update point radii based on hover state
+        let dont_select = matches!(
+            *mode,
+            CursorMode::Delete | CursorMode::Trim | CursorMode::Insert
+        );
+        let radius = lerp(
+            Self::RADIUS..=Self::HOVER_RADIUS,
+            ctx.animate_bool(id, !dont_select && active),
+        );
+        let color = Color32::YELLOW.lerp_to_gamma(
+            Color32::RED,
+            ctx.animate_bool(
+                id.with(0),
+                (*mode == CursorMode::Trim) || ((*mode == CursorMode::Delete) && hovered),
+            ),
+        );
+
+        // Draw point, with a tick showing the heading to face and a dot if it runs markers
+        let center = self.screen(ratio, origin);
+        ui.painter()
+            .circle_stroke(center, radius, Stroke::new(2.0, color));
+        if !self.markers.is_empty() {
+            ui.painter()
+                .circle_filled(center, Self::MARKER_RADIUS, Color32::ORANGE);
+        }
+        if let Some(heading) = self.heading {
+            let direction = Vec2::angled(heading.to_radians());
+            ui.painter().line_segment(
+                [
+                    center + direction * radius,
+                    center + direction * (radius + 6.0),
+                ],
+                Stroke::new(2.0, color),
+            );
+        }
+    }
+
+    /// Gets the drawn radius of the point.
+    pub(crate) fn get_radius(active: bool) -> f32 {
+        if active {
+            Self::HOVER_RADIUS
+        } else {
+            Self::RADIUS
+        }
+    }
+}
@@ -0,0 +1,503 @@
+use std::sync::Arc;
+
+use egui::{pos2, Pos2};
+
+/// Where the origin of a field's coordinates sits.
+/// Pathy works from the top left with y pointing down,
+/// while the other origins point y up like most robot code.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Origin {
+    #[default]
+    TopLeft,
+    BottomLeft,
+    Center,
+}
+
+/// Coordinates positions are shown and written out in.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct Frame {
+    pub origin: Origin,
+    /// Field width, in inches
+    pub width: f32,
+    /// Field height, in inches
+    pub height: f32,
+}
+
+impl Default for Frame {
+    fn default() -> Self {
+        Self {
+            origin: Origin::TopLeft,
+            width: 140.5,
+            height: 140.5,
+        }
+    }
+}
+
+impl Frame {
+    /// Converts a position from Pathy's coordinates into this frame.
+    pub fn to_frame(&self, pos: Pos2) -> Pos2 {
+        match self.origin {
+            Origin::TopLeft => pos,
+            Origin::BottomLeft => pos2(pos.x, self.height - pos.y),
+            Origin::Center => pos2(pos.x - self.width / 2.0, self.height / 2.0 - pos.y),
+        }
+    }
+
+    /// Converts a position in this frame back into Pathy's coordinates.
+    pub fn from_frame(&self, pos: Pos2) -> Pos2 {
+        match self.origin {
+            Origin::TopLeft => pos,
+            Origin::BottomLeft => pos2(pos.x, self.height - pos.y),
+            Origin::Center => pos2(pos.x + self.width / 2.0, self.height / 2.0 - pos.y),
+        }
+    }
+
+    /// Converts a heading in degrees, clockwise in Pathy's coordinates,
+    /// into this frame. Frames with y up measure headings counterclockwise.
+    /// Converting twice gives back the original heading.
+    pub fn heading(&self, degrees: f32) -> f32 {
+        match self.origin {
+            Origin::TopLeft => degrees,
+            Origin::BottomLeft | Origin::Center => -degrees,
+        }
+    }
+}
+
+/// Picture drawn under the path.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, Default, PartialEq)]
+pub enum FieldImage {
+    #[default]
+    None,
+    PushBack,
+    PushBackSkills,
+    /// Encoded image data, held with the field
+    Custom(Arc<[u8]>),
+    /// An uploaded image, kept by name in the app's image store
+    Stored(String),
+}
+
+impl FieldImage {
+    /// Encoded image data, if there is an image.
+    /// Stored images have to be looked up in the image store instead.
+    pub fn bytes(&self) -> Option<&[u8]> {
+        match self {
+            FieldImage::None | FieldImage::Stored(_) => None,
+            FieldImage::PushBack => Some(include_bytes!("../assets/pushback.png")),
+            FieldImage::PushBackSkills => Some(include_bytes!("../assets/pushback-skills.png")),
+            FieldImage::Custom(bytes) => Some(bytes),
+        }
+    }
+
+    /// Name to show when choosing an image.
+    pub fn label(&self) -> &str {
+        match self {
+            FieldImage::None => "None",
+            FieldImage::PushBack => "Push Back",
+            FieldImage::PushBackSkills => "Push Back Skills",
+            FieldImage::Custom(_) => "Custom",
+            FieldImage::Stored(name) => name,
+        }
+    }
+}
+
+/// Something on the field the robot can't drive through, in Pathy's coordinates.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+pub enum Obstacle {
+    Rect {
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+    },
+    Circle {
+        x: f32,
+        y: f32,
+        radius: f32,
+    },
+}
+
+impl Obstacle {
+    /// Whether a position is inside the obstacle.
+    pub fn contains(&self, pos: Pos2) -> bool {
+        match *self {
+            Obstacle::Rect {
+                x,
+                y,
+                width,
+                height,
+            } => pos.x >= x && pos.x <= x + width && pos.y >= y && pos.y <= y + height,
+            Obstacle::Circle { x, y, radius } => pos.distance(pos2(x, y)) <= radius,
+        }
+    }
+}
+
+/// A known spot on the field, matched to where it is in the field's image.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Debug, PartialEq)]
+pub struct Landmark {
+    /// Position on the field, in Pathy's coordinates
+    pub x: f32,
+    pub y: f32,
+    /// Position in the image, in pixels
+    pub image_x: f32,
+    pub image_y: f32,
+}
+
+/// A projective transform, mapping field positions to image pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Homography([f32; 9]);
+
+impl Homography {
+    /// Fits a transform through landmarks: rotation, scale and offset from two,
+    /// or a full perspective correction from four. Any other number can't be fitted.
+    pub fn fit(landmarks: &[Landmark]) -> Option<Homography> {
+        match landmarks {
+            [a, b] => Self::similarity(a, b),
+            [_, _, _, _] => Self::perspective(landmarks),
+            _ => None,
+        }
+    }
+
+    /// The rotation, uniform scale and offset taking two field positions to their pixels,
+    /// treating positions as complex numbers: `image = scale * field + offset`.
+    fn similarity(a: &Landmark, b: &Landmark) -> Option<Homography> {
+        let (dx, dy) = (b.x - a.x, b.y - a.y);
+        let (ix, iy) = (b.image_x - a.image_x, b.image_y - a.image_y);
+        let length = dx * dx + dy * dy;
+        if length <= f32::EPSILON {
+            return None;
+        }
+        // Complex division (ix + iy i) / (dx + dy i)
+        let re = (ix * dx + iy * dy) / length;
+        let im = (iy * dx - ix * dy) / length;
+        let tx = a.image_x - (re * a.x - im * a.y);
+        let ty = a.image_y - (im * a.x + re * a.y);
+        Some(Homography([re, -im, tx, im, re, ty, 0.0, 0.0, 1.0]))
+    }
+
+    /// The perspective transform taking four field positions to their pixels,
+    /// solving the eight unknowns of the matrix with its last entry fixed at one.
+    fn perspective(landmarks: &[Landmark]) -> Option<Homography> {
+        let mut rows = [[0.0f64; 9]; 8];
+        for (i, l) in landmarks.iter().enumerate() {
+            let (x, y) = (l.x as f64, l.y as f64);
+            let (u, v) = (l.image_x as f64, l.image_y as f64);
+            rows[2 * i] = [x, y, 1.0, 0.0, 0.0, 0.0, -u * x, -u * y, u];
+            rows[2 * i + 1] = [0.0, 0.0, 0.0, x, y, 1.0, -v * x, -v * y, v];
+        }
+        // Gaussian elimination with partial pivoting
+        for col in 0..8 {
+            let pivot =
+                (col..8).max_by(|&a, &b| rows[a][col].abs().total_cmp(&rows[b][col].abs()))?;
+            if rows[pivot][col].abs() < 1e-9 {
+                return None;
+            }
+            rows.swap(col, pivot);
+            for row in 0..8 {
+                if row != col {
+                    let factor = rows[row][col] / rows[col][col];
+                    for k in col..9 {
+                        rows[row][k] -= factor * rows[col][k];
+                    }
+                }
+            }
+        }
+        let mut matrix = [1.0f32; 9];
+        for (i, row) in rows.iter().enumerate() {
+            matrix[i] = (row[8] / row[i]) as f32;
+        }
+        Some(Homography(matrix))
+    }
+
+    /// Maps a field position to its image pixel.
+    pub fn apply(&self, pos: Pos2) -> Pos2 {
+        let m = &self.0;
+        let w = m[6] * pos.x + m[7] * pos.y + m[8];
+        pos2(
+            (m[0] * pos.x + m[1] * pos.y + m[2]) / w,
+            (m[3] * pos.x + m[4] * pos.y + m[5]) / w,
+        )
+    }
+}
+
+/// A field definition: its picture, real size, coordinates and obstacles.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct Field {
+    pub name: String,
+    pub image: FieldImage,
+    /// Real width, in inches
+    pub width: f32,
+    /// Real height, in inches
+    pub height: f32,
+    /// Where positions are measured from
+    pub origin: Origin,
+    pub obstacles: Vec<Obstacle>,
+    /// Landmarks lining the image up with the field.
+    /// Without two or four, the image is stretched over the whole field.
+    pub calibration: Vec<Landmark>,
+}
+
+impl Default for Field {
+    fn default() -> Self {
+        Self {
+            name: "Push Back".into(),
+            image: FieldImage::PushBack,
+            width: V5_SIZE,
+            height: V5_SIZE,
+            origin: Origin::TopLeft,
+            obstacles: push_back_obstacles(),
+            calibration: Vec::new(),
+        }
+    }
+}
+
+/// Inside size of a 12 foot VEX V5 and VEX U field, in inches.
+const V5_SIZE: f32 = 140.5;
+/// Inside size of a 6 by 8 foot VEX IQ field, in inches.
+const VIQ_SIZE: (f32, f32) = (72.0, 96.0);
+/// Size of a V5 field tile, six to a side.
+const TILE: f32 = V5_SIZE / 6.0;
+
+/// A rectangle obstacle of a size, centered `dx` and `dy` inches from the middle of a V5 field.
+fn centered_rect(dx: f32, dy: f32, width: f32, height: f32) -> Obstacle {
+    Obstacle::Rect {
+        x: V5_SIZE / 2.0 + dx - width / 2.0,
+        y: V5_SIZE / 2.0 + dy - height / 2.0,
+        width,
+        height,
+    }
+}
+
+/// A circle obstacle, centered `dx` and `dy` inches from the middle of a V5 field.
+fn centered_circle(dx: f32, dy: f32, radius: f32) -> Obstacle {
+    Obstacle::Circle {
+        x: V5_SIZE / 2.0 + dx,
+        y: V5_SIZE / 2.0 + dy,
+        radius,
+    }
+}
+
+/// Push Back: the two long goals in line with the loaders, and the crossed center goals.
+fn push_back_obstacles() -> Vec<Obstacle> {
+    vec![
+        centered_rect(0.0, -2.0 * TILE, 48.8, 5.0),
+        centered_rect(0.0, 2.0 * TILE, 48.8, 5.0),
+        centered_circle(0.0, 0.0, 9.0),
+    ]
+}
+
+impl Field {
+    /// Fields that come with Pathy, newest first, with the fixed structures on them as obstacles.
+    /// VEX U plays on the V5 field. Fields without a bundled picture take an uploaded one.
+    pub fn builtin() -> Vec<Field> {
+        let v5 = |name: &str, image, obstacles| Field {
+            name: name.into(),
+            image,
+            obstacles,
+            ..Default::default()
+        };
+        let viq = |name: &str| Field {
+            name: name.into(),
+            image: FieldImage::None,
+            width: VIQ_SIZE.0,
+            height: VIQ_SIZE.1,
+            obstacles: Vec::new(),
+            ..Default::default()
+        };
+        vec![
+            v5("Push Back", FieldImage::PushBack, push_back_obstacles()),
+            v5(
+                "Push Back Skills",
+                FieldImage::PushBackSkills,
+                push_back_obstacles(),
+            ),
+            // The ladder in the middle
+            v5(
+                "High Stakes",
+                FieldImage::None,
+                vec![centered_circle(0.0, 0.0, TILE)],
+            ),
+            // The goals on the top and bottom walls, and the elevation bars
+            // in front of each alliance station
+            v5(
+                "Over Under",
+                FieldImage::None,
+                vec![
+                    centered_rect(0.0, -2.5 * TILE, 2.0 * TILE, TILE),
+                    centered_rect(0.0, 2.5 * TILE, 2.0 * TILE, TILE),
+                    centered_rect(-2.5 * TILE, 0.0, TILE, 4.0),
+                    centered_rect(2.5 * TILE, 0.0, TILE, 4.0),
+                ],
+            ),
+            // The high goals in opposite corners
+            v5(
+                "Spin Up",
+                FieldImage::None,
+                vec![
+                    centered_circle(-2.25 * TILE, 2.25 * TILE, 12.0),
+                    centered_circle(2.25 * TILE, -2.25 * TILE, 12.0),
+                ],
+            ),
+            viq("VIQ Mix & Match"),
+            viq("VIQ Rapid Relay"),
+            viq("VIQ Full Volume"),
+        ]
+    }
+
+    /// Coordinates positions on this field are shown in.
+    pub fn frame(&self) -> Frame {
+        Frame {
+            origin: self.origin,
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    /// Maps field positions to pixels of the image, if it's calibrated.
+    pub fn image_transform(&self) -> Option<Homography> {
+        Homography::fit(&self.calibration)
+    }
+
+    /// Landmarks to click when calibrating with two or four points:
+    /// opposite corners, or every corner clockwise from the top left.
+    pub fn corner_landmarks(&self, count: usize) -> Vec<Landmark> {
+        let (w, h) = (self.width, self.height);
+        let corners: &[(f32, f32)] = if count == 2 {
+            &[(0.0, 0.0), (w, h)]
+        } else {
+            &[(0.0, 0.0), (w, 0.0), (w, h), (0.0, h)]
+        };
+        corners
+            .iter()
+            .map(|&(x, y)| Landmark {
+                x,
+                y,
+                image_x: 0.0,
+                image_y: 0.0,
+            })
+            .collect()
+    }
+
+    /// Whether a position is on the field and clear of every obstacle.
+    pub fn is_clear(&self, pos: Pos2) -> bool {
+        pos.x >= 0.0
+            && pos.y >= 0.0
+            && pos.x <= self.width
+            && pos.y <= self.height
+            && !self.obstacles.iter().any(|o| o.contains(pos))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_round_trip() {
+        let pos = pos2(10.0, 20.0);
+        for origin in [Origin::TopLeft, Origin::BottomLeft, Origin::Center] {
+            let frame = Frame {
+                origin,
+                width: 72.0,
+                height: 96.0,
+            };
+            assert_eq!(frame.from_frame(frame.to_frame(pos)), pos);
+        }
+        let center = Frame {
+            origin: Origin::Center,
+            width: 72.0,
+            height: 96.0,
+        };
+        assert_eq!(center.to_frame(pos), pos2(-26.0, 28.0));
+    }
+
+    fn landmark(x: f32, y: f32, image: Pos2) -> Landmark {
+        Landmark {
+            x,
+            y,
+            image_x: image.x,
+            image_y: image.y,
+        }
+    }
+
+    #[test]
+    fn two_landmarks_fit_rotation_and_scale() {
+        // The image is the field turned a quarter clockwise, at 2 pixels per inch, offset by 100
+        let map = |x: f32, y: f32| pos2(100.0 - 2.0 * y, 100.0 + 2.0 * x);
+        let h = Homography::fit(&[
+            landmark(0.0, 0.0, map(0.0, 0.0)),
+            landmark(10.0, 20.0, map(10.0, 20.0)),
+        ])
+        .unwrap();
+        let mapped = h.apply(pos2(30.0, 5.0));
+        assert!(mapped.distance(map(30.0, 5.0)) < 1e-3, "{mapped:?}");
+    }
+
+    #[test]
+    fn four_landmarks_fit_perspective() {
+        // A field photographed at an angle: the far side appears narrower
+        let corners = [
+            landmark(0.0, 0.0, pos2(200.0, 100.0)),
+            landmark(100.0, 0.0, pos2(600.0, 100.0)),
+            landmark(100.0, 100.0, pos2(800.0, 500.0)),
+            landmark(0.0, 100.0, pos2(0.0, 500.0)),
+        ];
+        let h = Homography::fit(&corners).unwrap();
+        for corner in &corners {
+            let mapped = h.apply(pos2(corner.x, corner.y));
+            assert!(mapped.distance(pos2(corner.image_x, corner.image_y)) < 1e-2);
+        }
+        // Perspective keeps straight lines straight, so the center is where the diagonals cross
+        assert!(h.apply(pos2(50.0, 50.0)).distance(pos2(400.0, 233.333)) < 1e-2);
+        assert!(Homography::fit(&corners[..3]).is_none());
+    }
+
+    #[test]
+    fn builtin_obstacles_stay_on_the_field() {
+        for field in Field::builtin() {
+            for obstacle in &field.obstacles {
+                let (min, max) = match *obstacle {
+                    Obstacle::Rect {
+                        x,
+                        y,
+                        width,
+                        height,
+                    } => (pos2(x, y), pos2(x + width, y + height)),
+                    Obstacle::Circle { x, y, radius } => {
+                        (pos2(x - radius, y - radius), pos2(x + radius, y + radius))
+                    }
+                };
+                assert!(
+                    min.x >= 0.0 && min.y >= 0.0 && max.x <= field.width && max.y <= field.height,
+                    "{obstacle:?} is off the {} field",
+                    field.name
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn obstacles_block_positions() {
+        let field = Field {
+            obstacles: vec![
+                Obstacle::Rect {
+                    x: 10.0,
+                    y: 10.0,
+                    width: 5.0,
+                    height: 5.0,
+                },
+                Obstacle::Circle {
+                    x: 50.0,
+                    y: 50.0,
+                    radius: 3.0,
+                },
+            ],
+            ..Default::default()
+        };
+        assert!(!field.is_clear(pos2(12.0, 12.0)));
+        assert!(!field.is_clear(pos2(51.0, 51.0)));
+        assert!(!field.is_clear(pos2(-1.0, 5.0)));
+        assert!(field.is_clear(pos2(30.0, 30.0)));
+    }
+}
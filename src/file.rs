@@ -0,0 +1,37 @@
+/// Saves a file for the user: downloaded by the browser on the web,
+/// or written to the working directory natively.
+/// Returns a description of where the file went.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_file(name: &str, bytes: &[u8]) -> Result<String, String> {
+    let path = std::env::current_dir()
+        .map_err(|e| format!("Failed to find working directory: {e}"))?
+        .join(name);
+    std::fs::write(&path, bytes).map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+    Ok(format!("Saved {}", path.display()))
+}
+
+/// Saves a file for the user: downloaded by the browser on the web,
+/// or written to the working directory natively.
+/// Returns a description of where the file went.
+#[cfg(target_arch = "wasm32")]
+pub fn save_file(name: &str, bytes: &[u8]) -> Result<String, String> {
+    use eframe::wasm_bindgen::JsCast as _;
+
+    let fail = |e: eframe::wasm_bindgen::JsValue| format!("Failed to download {name}: {e:?}");
+    let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(bytes));
+    let blob = web_sys::Blob::new_with_u8_array_sequence(&parts).map_err(fail)?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob).map_err(fail)?;
+    let document = web_sys::window()
+        .and_then(|w| w.document())
+        .ok_or("No document")?;
+    let link = document
+        .create_element("a")
+        .map_err(fail)?
+        .dyn_into::<web_sys::HtmlAnchorElement>()
+        .map_err(|_| "Failed to create download link")?;
+    link.set_href(&url);
+    link.set_download(name);
+    link.click();
+    web_sys::Url::revoke_object_url(&url).map_err(fail)?;
+    Ok(format!("Downloaded {name}"))
+}
@@ -1,56 +1,955 @@
-use egui::{Pos2, Vec2};
+use std::collections::HashSet;
 
-use std::{cell::RefCell, rc::Rc};
+use egui::{pos2, Pos2, Vec2};
 
+use crate::field::Frame;
+use crate::geometry::{segments, wrap_angle, Segment, TURN_THRESHOLD};
+use crate::path::{Branch, Path};
 use crate::point::Point;
+use crate::pursuit::{sample, PursuitOptions};
+use crate::robot::Robot;
+use crate::trajectory::{self, TrajectoryOptions};
 
-/// Formats a number to a string,
-fn format_num(num: f32, precision: usize) -> String {
-    format!("{:.1$}", num, precision)
-        .trim_end_matches('0')
-        .trim_end_matches('.')
-        .to_string()
+/// The kind of output produced from a path.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    /// Turn, drive and arc calls on the chassis
+    Chassis,
+    /// Sampled pure pursuit path, as a C++ array
+    PursuitArray,
+    /// Sampled pure pursuit path, as a file for the SD card
+    PursuitFile,
+    /// Timed trajectory states, as CSV
+    TrajectoryCsv,
+    /// Timed trajectory states, as a C++ header
+    TrajectoryHeader,
 }
 
-/// Generates path code from a path.
-pub fn generate(path: &[Rc<RefCell<Point>>]) -> String {
-    if path.len() < 2 {
-        return "// Create two points to get started".into();
+/// How arcs are written in generated code.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArcStyle {
+    /// `chassis.arc(radius, angle)`
+    Chassis,
+    /// `chassis.tank(left, right)`, with the distance covered by each side
+    Wheels,
+}
+
+/// How branches are written in chassis code.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BranchStyle {
+    /// The branch's commands inside the `if` block
+    IfElse,
+    /// A function per branch, called from the `if` block
+    Functions,
+}
+
+/// Units that drive distances are written in.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DriveUnit {
+    /// Field inches
+    Inches,
+    /// Motor degrees
+    Degrees,
+    /// Motor rotations
+    Rotations,
+    /// Motor encoder ticks
+    Ticks,
+}
+
+impl DriveUnit {
+    /// Converts a distance in inches into this unit for a robot.
+    pub fn convert(&self, inches: f32, robot: &Robot) -> f32 {
+        match self {
+            DriveUnit::Inches => inches,
+            DriveUnit::Degrees => robot.motor_rotations(inches) * 360.0,
+            DriveUnit::Rotations => robot.motor_rotations(inches),
+            DriveUnit::Ticks => robot.motor_rotations(inches) * robot.ticks_per_rotation,
+        }
+    }
+
+    /// Converts a distance in this unit back into inches for a robot.
+    pub fn to_inches(&self, value: f32, robot: &Robot) -> f32 {
+        value / self.convert(1.0, robot)
+    }
+
+    /// Short label shown after distances in this unit.
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            DriveUnit::Inches => "in",
+            DriveUnit::Degrees => "deg",
+            DriveUnit::Rotations => "rot",
+            DriveUnit::Ticks => "ticks",
+        }
+    }
+}
+
+/// User-editable code templates for the chassis backend.
+///
+/// Command templates fill in `{distance}`, `{angle}`, `{radius}`, `{left}`, `{right}`,
+/// `{speed}` and `{code}` from the command, and `{x}`, `{y}` and `{heading}`
+/// from where the robot ends up after it.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct Templates {
+    /// Text before the code
+    pub header: String,
+    /// Text around the commands, which replace `{body}`
+    pub wrapper: String,
+    /// Text after the code
+    pub footer: String,
+    pub drive: String,
+    pub turn: String,
+    /// Arcs, when written with [`ArcStyle::Chassis`]
+    pub arc: String,
+    /// Arcs, when written with [`ArcStyle::Wheels`]
+    pub tank: String,
+    pub speed: String,
+    pub marker: String,
+    /// Decimal places numbers are written with
+    pub precision: usize,
+}
+
+impl Default for Templates {
+    fn default() -> Self {
+        Self {
+            header: "// Generated by Pathy".into(),
+            wrapper: "{body}".into(),
+            footer: String::new(),
+            drive: "chassis.drive({distance});".into(),
+            turn: "chassis.turn({angle});".into(),
+            arc: "chassis.arc({radius}, {angle});".into(),
+            tank: "chassis.tank({left}, {right});".into(),
+            speed: "chassis.set_speed({speed});".into(),
+            marker: "{code}".into(),
+            precision: 2,
+        }
+    }
+}
+
+impl Templates {
+    /// Whether commands are written the default way, which can be parsed back into a path.
+    pub fn default_commands(&self) -> bool {
+        let default = Self::default();
+        self.drive == default.drive
+            && self.turn == default.turn
+            && self.arc == default.arc
+            && self.tank == default.tank
+            && self.speed == default.speed
+            && self.marker == default.marker
+    }
+}
+
+/// Replaces every `{name}` in a template with its value.
+fn fill(template: &str, values: &[(&str, String)]) -> String {
+    values
+        .iter()
+        .fold(template.to_string(), |text, (name, value)| {
+            text.replace(&format!("{{{name}}}"), value)
+        })
+}
+
+/// Settings that control generated code.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct GenerateOptions {
+    /// Kind of output to generate
+    pub backend: Backend,
+    /// How arcs are emitted
+    pub arc_style: ArcStyle,
+    /// Units for drive distances
+    pub drive_unit: DriveUnit,
+    /// Pure pursuit sampling settings
+    pub pursuit: PursuitOptions,
+    /// Trajectory timing settings
+    pub trajectory: TrajectoryOptions,
+    /// How chassis code is written
+    pub templates: Templates,
+    /// Coordinates `{x}`, `{y}` and `{heading}` are written in
+    pub frame: Frame,
+    /// How branches are written
+    pub branch_style: BranchStyle,
+}
+
+impl Default for GenerateOptions {
+    fn default() -> Self {
+        Self {
+            backend: Backend::Chassis,
+            arc_style: ArcStyle::Chassis,
+            drive_unit: DriveUnit::Inches,
+            pursuit: PursuitOptions::default(),
+            trajectory: TrajectoryOptions::default(),
+            templates: Templates::default(),
+            frame: Frame::default(),
+            branch_style: BranchStyle::IfElse,
+        }
+    }
+}
+
+/// A single chassis movement.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    /// Drive straight by a distance, negative being backwards.
+    Drive(f32),
+    /// Turn in place by an angle in degrees, positive being clockwise.
+    Turn(f32),
+    /// Follow an arc of the radius through an angle in degrees, positive being clockwise.
+    Arc { radius: f32, angle: f32 },
+    /// Limit the speed of following movements to a percentage of top speed.
+    Speed(f32),
+    /// Run a line of code from a waypoint marker.
+    Marker(String),
+}
+
+/// Formats a number to a string, dropping trailing zeros.
+pub(crate) fn format_num(num: f32, precision: usize) -> String {
+    let text = format!("{:.1$}", num, precision);
+    let text = if text.contains('.') {
+        text.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        &text
+    };
+    // Tiny negative numbers round to zero, which shouldn't keep their sign
+    if text == "-0" {
+        "0".into()
+    } else {
+        text.to_string()
+    }
+}
+
+/// Converts the segments of a path into chassis movements.
+pub fn commands(path: &[Point]) -> Vec<Command> {
+    segment_commands(path)
+        .into_iter()
+        .flat_map(|(_, commands)| commands)
+        .collect()
+}
+
+/// Pairs each segment of a path with the chassis movements it produces,
+/// including the turn that lines the robot up before a straight segment.
+/// Turns to a waypoint's heading and its markers follow the last segment reaching it,
+/// and markers on the start come before the first segment.
+pub fn segment_commands(path: &[Point]) -> Vec<(Segment, Vec<Command>)> {
+    drive(path, None).commands
+}
+
+/// Commands driving a path, and how the robot is left after them.
+struct Drive {
+    commands: Vec<(Segment, Vec<Command>)>,
+    /// Direction the front of the robot ends up facing, in radians
+    heading: Option<f32>,
+    /// Speed limit the robot ends up with
+    speed: Option<f32>,
+}
+
+/// Direction the robot starts a path facing, in radians:
+/// the first point's heading, or the direction of the first segment if it has none.
+pub(crate) fn start_heading(path: &[Point]) -> Option<f32> {
+    if let Some(heading) = path.first().and_then(|p| p.heading) {
+        return Some(heading.to_radians());
+    }
+    segments(&waypoints(path))
+        .into_iter()
+        .find_map(|(pair, segment)| match segment {
+            Segment::Line { start, end } if start.distance(end) > f32::EPSILON => {
+                let angle = (end - start).angle();
+                Some(if path[pair + 1].reverse {
+                    angle + std::f32::consts::PI
+                } else {
+                    angle
+                })
+            }
+            Segment::Line { .. } => None,
+            Segment::Arc {
+                start_angle, sweep, ..
+            } => Some(start_angle + std::f32::consts::FRAC_PI_2 * sweep.signum()),
+        })
+}
+
+/// Marker commands for the code on a point.
+fn markers(point: &Point) -> impl Iterator<Item = Command> + '_ {
+    point
+        .markers
+        .iter()
+        .filter(|code| !code.trim().is_empty())
+        .map(|code| Command::Marker(code.trim().into()))
+}
+
+/// Converts a path into chassis movements, for a robot already limited to `speed`.
+fn drive(path: &[Point], speed: Option<f32>) -> Drive {
+    let mut result: Vec<(Segment, Vec<Command>)> = Vec::new();
+    // Direction the front of the robot faces
+    let mut heading = start_heading(path);
+    let mut speed = speed;
+    let segments = segments(&waypoints(path));
+    for (i, &(pair, segment)) in segments.iter().enumerate() {
+        let target = &path[pair + 1];
+        let mut commands = Vec::new();
+        // Markers on the start run before the robot moves
+        if i == 0 {
+            commands.extend(markers(&path[0]));
+        }
+        if target.speed != speed {
+            speed = target.speed;
+            commands.push(Command::Speed(speed.unwrap_or(100.0)));
+        }
+        match segment {
+            Segment::Line { start, end } => {
+                let length = start.distance(end);
+                if length > f32::EPSILON {
+                    let mut angle = (end - start).angle();
+                    if target.reverse {
+                        angle += std::f32::consts::PI;
+                    }
+                    if let Some(heading) = heading {
+                        let turn = wrap_angle(angle - heading);
+                        if turn.abs() > TURN_THRESHOLD {
+                            commands.push(Command::Turn(turn.to_degrees()));
+                        }
+                    }
+                    heading = Some(angle);
+                    commands.push(Command::Drive(if target.reverse {
+                        -length
+                    } else {
+                        length
+                    }));
+                }
+            }
+            Segment::Arc { radius, sweep, .. } => {
+                heading = heading.map(|heading| heading + sweep);
+                commands.push(Command::Arc {
+                    radius,
+                    angle: sweep.to_degrees(),
+                });
+            }
+        }
+        let reached = segments.get(i + 1).map_or(true, |(next, _)| *next != pair);
+        if reached {
+            if let Some(target_heading) = target.heading.map(f32::to_radians) {
+                if let Some(heading) = heading {
+                    let turn = wrap_angle(target_heading - heading);
+                    if turn.abs() > TURN_THRESHOLD {
+                        commands.push(Command::Turn(turn.to_degrees()));
+                    }
+                }
+                heading = Some(target_heading);
+            }
+            commands.extend(markers(target));
+        }
+        result.push((segment, commands));
+    }
+    Drive {
+        commands: result,
+        heading,
+        speed,
+    }
+}
+
+/// Formats a single command as a line of code,
+/// for a robot ending up at `pos` facing `heading` radians afterwards.
+fn format_command(
+    command: &Command,
+    pos: Pos2,
+    heading: f32,
+    robot: &Robot,
+    options: &GenerateOptions,
+) -> String {
+    let templates = &options.templates;
+    let num = |num: f32| format_num(num, templates.precision);
+    let unit = options.drive_unit;
+    let frame = &options.frame;
+    let pos = frame.to_frame(pos);
+    let mut values = vec![
+        ("x", num(pos.x)),
+        ("y", num(pos.y)),
+        (
+            "heading",
+            num(frame.heading(wrap_angle(heading).to_degrees())),
+        ),
+    ];
+    let template = match *command {
+        Command::Drive(length) => {
+            values.push(("distance", num(unit.convert(length, robot))));
+            &templates.drive
+        }
+        Command::Turn(angle) => {
+            values.push(("angle", num(angle)));
+            &templates.turn
+        }
+        Command::Arc { radius, angle } => {
+            values.push(("radius", num(radius)));
+            values.push(("angle", num(angle)));
+            match options.arc_style {
+                ArcStyle::Chassis => &templates.arc,
+                ArcStyle::Wheels => {
+                    // The outside of a clockwise turn is the left side
+                    let offset = robot.track_width / 2.0 * angle.signum();
+                    let radians = angle.abs().to_radians();
+                    values.push((
+                        "left",
+                        num(unit.convert((radius + offset) * radians, robot)),
+                    ));
+                    values.push((
+                        "right",
+                        num(unit.convert((radius - offset) * radians, robot)),
+                    ));
+                    &templates.tank
+                }
+            }
+        }
+        Command::Speed(percent) => {
+            values.push(("speed", num(percent)));
+            &templates.speed
+        }
+        Command::Marker(ref code) => {
+            values.push(("code", code.clone()));
+            &templates.marker
+        }
+    };
+    fill(template, &values)
+}
+
+/// Formats commands driven from the start of a path as lines of code.
+pub(crate) fn format_commands(
+    path: &[Point],
+    commands: &[Command],
+    robot: &Robot,
+    options: &GenerateOptions,
+) -> Vec<String> {
+    let mut pos = path.first().map_or(Pos2::ZERO, |p| pos2(p.x, p.y));
+    let mut heading = start_heading(path).unwrap_or(0.0);
+    commands
+        .iter()
+        .map(|command| {
+            match *command {
+                Command::Drive(length) => pos += Vec2::angled(heading) * length,
+                Command::Turn(angle) => heading += angle.to_radians(),
+                Command::Arc { radius, angle } => {
+                    // Clockwise arcs curve around a center on the robot's right
+                    let side = std::f32::consts::FRAC_PI_2 * angle.signum();
+                    let center = pos + Vec2::angled(heading + side) * radius;
+                    heading += angle.to_radians();
+                    pos = center + Vec2::angled(heading - side) * radius;
+                }
+                Command::Speed(_) | Command::Marker(_) => {}
+            }
+            format_command(command, pos, heading, robot, options)
+        })
+        .collect()
+}
+
+/// Generates chassis code, wrapped in the header, wrapper and footer templates.
+fn generate_chassis(path: &[Point], robot: &Robot, options: &GenerateOptions) -> String {
+    let lines = format_commands(path, &commands(path), robot, options);
+    wrap_chassis(&lines, &[], &options.templates)
+}
+
+/// Chassis code driving part of a path, for a robot starting at its first point
+/// facing `heading` radians (or along its first segment, if unknown) and limited to `speed`.
+/// Markers on the first point are left to the code that reached it.
+fn chassis_piece(
+    path: &[Point],
+    heading: Option<f32>,
+    speed: Option<f32>,
+    robot: &Robot,
+    options: &GenerateOptions,
+) -> (Vec<String>, Drive) {
+    let mut path = path.to_vec();
+    if let Some(first) = path.first_mut() {
+        first.heading = heading.map(f32::to_degrees);
+        first.markers.clear();
+    }
+    let drive = drive(&path, speed);
+    let commands: Vec<Command> = drive.commands.iter().flat_map(|(_, c)| c.clone()).collect();
+    (format_commands(&path, &commands, robot, options), drive)
+}
+
+/// Name a branch's function is written with, made into a valid identifier
+/// and given a numeric suffix if it's already in `used`.
+fn function_name(branch: &Branch, index: usize, used: &mut HashSet<String>) -> String {
+    let name: String = branch
+        .name
+        .trim()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    let name = if name.is_empty() || name.starts_with(|c: char| c.is_numeric()) {
+        format!("branch_{}{name}", index + 1)
+    } else {
+        name
+    };
+    let name = std::iter::once(name.clone())
+        .chain((2..).map(|n| format!("{name}_{n}")))
+        .find(|name| !used.contains(name))
+        .unwrap_or(name);
+    used.insert(name.clone());
+    name
+}
+
+/// Generates chassis code for a path with branches.
+///
+/// The main path is driven up to each branch, where an `if` on the branch's condition
+/// drives the branch and an `else` drives the main path up to where the branch rejoins it.
+/// A branch that doesn't rejoin ends the run, so the `else` drives the rest of the main path.
+/// Both sides reach the rejoining point facing the same way, so the code after them
+/// doesn't depend on which was taken.
+fn generate_branched(path: &Path, robot: &Robot, options: &GenerateOptions) -> String {
+    let points = path.points();
+    let mut branches: Vec<(usize, &Branch)> = path
+        .branches()
+        .iter()
+        .filter(|b| path.index_of(b.from).is_some())
+        .enumerate()
+        .collect();
+    branches.sort_by_key(|(_, b)| path.index_of(b.from));
+
+    let indent = |lines: Vec<String>| lines.into_iter().map(|line| format!("    {line}"));
+    let start: Vec<Command> = points.first().map(markers).into_iter().flatten().collect();
+    let mut lines = format_commands(points, &start, robot, options);
+    let mut functions = Vec::new();
+    let mut names = HashSet::new();
+    let mut cursor = 0;
+    // Branches leaving from the start face the same way as the main path
+    let mut heading = start_heading(points);
+    let mut speed = None;
+    for (i, branch) in branches {
+        let from = path.index_of(branch.from).unwrap_or_default();
+        if from < cursor {
+            lines.push(format!(
+                "// Skipped branch {}, which leaves from inside another branch",
+                branch.name
+            ));
+            continue;
+        }
+        let (before, drive) = chassis_piece(&points[cursor..=from], heading, speed, robot, options);
+        lines.extend(before);
+        (heading, speed) = (drive.heading, drive.speed);
+
+        let rejoin = branch
+            .rejoin
+            .and_then(|id| path.index_of(id))
+            .filter(|&r| r >= from);
+        let end = rejoin.unwrap_or(points.len() - 1);
+        let (otherwise, main) = chassis_piece(&points[from..=end], heading, speed, robot, options);
+
+        let mut detour = vec![points[from].clone()];
+        detour.extend(branch.points.iter().cloned());
+        if let Some(rejoin) = rejoin {
+            let mut target = points[rejoin].clone();
+            target.heading = target.heading.or(main.heading.map(f32::to_degrees));
+            detour.push(target);
+        }
+        let (then, _) = chassis_piece(&detour, heading, speed, robot, options);
+
+        lines.push(format!("if ({}) {{", branch.condition.trim()));
+        match options.branch_style {
+            BranchStyle::IfElse => lines.extend(indent(then)),
+            BranchStyle::Functions => {
+                let name = function_name(branch, i, &mut names);
+                lines.push(format!("    {name}();"));
+                functions.push(
+                    std::iter::once(format!("void {name}() {{"))
+                        .chain(indent(then))
+                        .chain(std::iter::once("}".to_string()))
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                );
+            }
+        }
+        if !otherwise.is_empty() {
+            lines.push("} else {".into());
+            lines.extend(indent(otherwise));
+        }
+        lines.push("}".into());
+        cursor = end;
+        (heading, speed) = (main.heading, main.speed);
+    }
+    let (after, _) = chassis_piece(&points[cursor..], heading, speed, robot, options);
+    lines.extend(after);
+    wrap_chassis(&lines, &functions, &options.templates)
+}
+
+/// Puts lines of chassis code together with the header, wrapper and footer templates,
+/// with any functions they call between the header and the wrapper.
+fn wrap_chassis(lines: &[String], functions: &[String], templates: &Templates) -> String {
+    let mut result = String::new();
+    if !templates.header.trim().is_empty() {
+        result.push_str(templates.header.trim_end());
+        result.push_str("\n\n");
+    }
+    for function in functions {
+        result.push_str(function);
+        result.push_str("\n\n");
+    }
+    // Commands line up with the indentation of the `{body}` placeholder
+    let indent: String = templates
+        .wrapper
+        .lines()
+        .find(|line| line.contains("{body}"))
+        .map(|line| line.chars().take_while(|c| c.is_whitespace()).collect())
+        .unwrap_or_default();
+    let body = lines.join(&format!("\n{indent}"));
+    let wrapped = if templates.wrapper.contains("{body}") {
+        templates.wrapper.replace("{body}", &body)
+    } else {
+        body
+    };
+    if !wrapped.trim().is_empty() {
+        result.push_str(wrapped.trim_end());
+        result.push('\n');
     }
-    // Group into unbroken paths
-    let points: Vec<Pos2> = path.iter().map(|p| p.borrow().clone().into()).collect();
+    if !templates.footer.trim().is_empty() {
+        result.push('\n');
+        result.push_str(templates.footer.trim_end());
+        result.push('\n');
+    }
+    result
+}
+
+/// Gets the position and fillet radius of every point in a path.
+///
+/// The robot stops at points with a heading to turn in place,
+/// and arcs are only driven forwards, so those corners are kept sharp.
+pub fn waypoints(path: &[Point]) -> Vec<(Pos2, f32)> {
+    path.iter()
+        .enumerate()
+        .map(|(i, p)| {
+            let reversing = p.reverse || path.get(i + 1).is_some_and(|next| next.reverse);
+            let radius = if p.heading.is_some() || reversing {
+                0.0
+            } else {
+                p.radius
+            };
+            (pos2(p.x, p.y), radius)
+        })
+        .collect()
+}
+
+/// Gets the position and fillet radius of every point on the main path of a path with branches.
+/// The robot stops where branches leave and rejoin, so those corners are kept sharp too.
+pub fn path_waypoints(path: &Path) -> Vec<(Pos2, f32)> {
+    let mut result = waypoints(path.points());
+    for branch in path.branches() {
+        for id in std::iter::once(branch.from).chain(branch.rejoin) {
+            if let Some(i) = path.index_of(id) {
+                result[i].1 = 0.0;
+            }
+        }
+    }
+    result
+}
+
+/// Generates a sampled pure pursuit path, one `x, y, curvature, velocity` row per sample.
+fn generate_pursuit(path: &[(Pos2, f32)], robot: &Robot, options: &GenerateOptions) -> String {
+    let rows = sample(path, robot, &options.pursuit).into_iter().map(|p| {
+        [
+            format_num(p.pos.x, 3),
+            format_num(p.pos.y, 3),
+            format_num(p.curvature, 4),
+            format_num(p.velocity, 3),
+        ]
+        .join(", ")
+    });
+    match options.backend {
+        Backend::PursuitArray => {
+            let mut result: String = "// Generated by Pathy\n// x, y, curvature, velocity\n".into();
+            result.push_str("const double path[][4] = {\n");
+            for row in rows {
+                result.push_str(&format!("    {{{row}}},\n"));
+            }
+            result.push_str("};\n");
+            result
+        }
+        _ => {
+            let mut result: String = "x, y, curvature, velocity\n".into();
+            for row in rows {
+                result.push_str(&row);
+                result.push('\n');
+            }
+            result
+        }
+    }
+}
+
+/// Generates a timed trajectory for a Ramsete controller, one state per row.
+fn generate_trajectory(path: &[(Pos2, f32)], robot: &Robot, options: &GenerateOptions) -> String {
+    let states = trajectory::generate(path, robot, &options.trajectory);
+    let columns =
+        "time, x, y, heading, linear velocity, angular velocity, left velocity, right velocity";
+    let rows = states.into_iter().map(|s| {
+        [
+            format_num(s.time, 3),
+            format_num(s.pos.x, 3),
+            format_num(s.pos.y, 3),
+            format_num(s.heading, 2),
+            format_num(s.velocity, 3),
+            format_num(s.angular_velocity, 4),
+            format_num(s.left, 3),
+            format_num(s.right, 3),
+        ]
+        .join(", ")
+    });
+    match options.backend {
+        Backend::TrajectoryHeader => {
+            let mut result: String = "// Generated by Pathy\n#pragma once\n\n".into();
+            result.push_str(&format!("// {columns}\n"));
+            result.push_str("const double trajectory[][8] = {\n");
+            for row in rows {
+                result.push_str(&format!("    {{{row}}},\n"));
+            }
+            result.push_str("};\n");
+            result
+        }
+        _ => {
+            let mut result = format!("{columns}\n");
+            for row in rows {
+                result.push_str(&row);
+                result.push('\n');
+            }
+            result
+        }
+    }
+}
+
+/// Generates path code from a path, including its branches.
+/// Only chassis code has branches, other backends follow the main path.
+pub fn generate_path(path: &Path, robot: &Robot, options: &GenerateOptions) -> String {
+    if path.branches().is_empty() || options.backend != Backend::Chassis || path.len() < 2 {
+        return generate(path.points(), robot, options);
+    }
+    generate_branched(path, robot, options)
+}
+
+/// Generates path code from a path.
+pub fn generate(points: &[Point], robot: &Robot, options: &GenerateOptions) -> String {
     if points.len() < 2 {
         return "// Create two points to get started".into();
     }
-    // actually using vectors this time :)
-    let vectors: Vec<Vec2> = points.windows(2).map(|w| w[1] - w[0]).collect();
-    let mut result: String = "// Generated by Pathy\n\n".into();
-    let mut heading = vectors.get(0).unwrap().angle();
-    let mut moves: Vec<(f32, Option<f32>)> = vec![(vectors.get(0).unwrap().length(), None)];
-    for vector in vectors.iter().skip(1) {
-        let length = vector.length();
-        let angle = vector.angle();
-        let pi = std::f32::consts::PI;
-        moves.push((
-            length,
-            if f32::abs(angle - heading) > f32::to_radians(0.5) {
-                Some(-f32::to_degrees(
-                    (heading - angle + pi).rem_euclid(2.0 * pi) - pi,
-                ))
-            } else {
-                None
-            },
-        ));
-        heading = angle;
+    let path = waypoints(points);
+    if matches!(
+        options.backend,
+        Backend::PursuitArray | Backend::PursuitFile
+    ) {
+        return generate_pursuit(&path, robot, options);
+    }
+    if matches!(
+        options.backend,
+        Backend::TrajectoryCsv | Backend::TrajectoryHeader
+    ) {
+        return generate_trajectory(&path, robot, options);
     }
-    // now return it all formatted
-    for (length, turn) in moves {
-        let mut addition = String::new();
-        if let Some(angle) = turn {
-            addition.push_str(&format!("chassis.turn({});\n", format_num(angle, 2)));
+    generate_chassis(points, robot, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a path of sharp corners.
+    fn path(points: &[(f32, f32)]) -> Vec<Point> {
+        points.iter().map(|&(x, y)| Point::new(x, y)).collect()
+    }
+
+    /// Gets the turns in a list of commands.
+    fn turns(commands: &[Command]) -> Vec<f32> {
+        commands
+            .iter()
+            .filter_map(|c| match c {
+                Command::Turn(angle) => Some(*angle),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn format_num_trims_trailing_zeros() {
+        assert_eq!(format_num(24.0, 2), "24");
+        assert_eq!(format_num(100.0, 2), "100");
+        assert_eq!(format_num(12.5, 2), "12.5");
+        assert_eq!(format_num(-90.0, 2), "-90");
+        assert_eq!(format_num(0.0, 2), "0");
+    }
+
+    #[test]
+    fn format_num_rounds_to_precision() {
+        assert_eq!(format_num(1.23456, 2), "1.23");
+        assert_eq!(format_num(1.996, 2), "2");
+        assert_eq!(format_num(-0.001, 2), "0");
+        assert_eq!(format_num(100.0, 0), "100");
+    }
+
+    #[test]
+    fn straight_path_only_drives() {
+        let commands = commands(&path(&[(0.0, 0.0), (24.0, 0.0), (48.0, 0.0)]));
+        assert_eq!(commands, vec![Command::Drive(24.0), Command::Drive(24.0)]);
+    }
+
+    #[test]
+    fn turn_sign_is_clockwise_positive() {
+        // Screen coordinates point down, so turning towards +y is a right turn
+        let right = commands(&path(&[(0.0, 0.0), (24.0, 0.0), (24.0, 24.0)]));
+        let left = commands(&path(&[(0.0, 0.0), (24.0, 0.0), (24.0, -24.0)]));
+        assert_eq!(turns(&right), vec![90.0]);
+        assert_eq!(turns(&left), vec![-90.0]);
+    }
+
+    #[test]
+    fn turns_belong_to_the_segment_they_line_up() {
+        let segments = segment_commands(&path(&[(0.0, 0.0), (24.0, 0.0), (24.0, 24.0)]));
+        let commands: Vec<Vec<Command>> = segments.into_iter().map(|(_, c)| c).collect();
+        assert_eq!(
+            commands,
+            vec![
+                vec![Command::Drive(24.0)],
+                vec![Command::Turn(90.0), Command::Drive(24.0)]
+            ]
+        );
+    }
+
+    #[test]
+    fn turns_wrap_around_180_degrees() {
+        // Heading just below 180 degrees, then just above -180 degrees: a 2 degree turn
+        let a = Vec2::angled(179.0_f32.to_radians()) * 24.0;
+        let b = Vec2::angled((-179.0_f32).to_radians()) * 24.0;
+        let commands = commands(&path(&[(0.0, 0.0), (a.x, a.y), (a.x + b.x, a.y + b.y)]));
+        let turns = turns(&commands);
+        assert_eq!(turns.len(), 1);
+        assert!((turns[0] - 2.0).abs() < 1e-3, "turned {}", turns[0]);
+    }
+
+    #[test]
+    fn small_turns_are_ignored() {
+        let turn = |degrees: f32| {
+            let end = Vec2::angled(degrees.to_radians()) * 24.0;
+            turns(&commands(&path(&[
+                (0.0, 0.0),
+                (24.0, 0.0),
+                (24.0 + end.x, end.y),
+            ])))
+        };
+        assert!(turn(0.4).is_empty());
+        assert_eq!(turn(0.6).len(), 1);
+    }
+
+    #[test]
+    fn fillets_replace_turns_with_arcs() {
+        let mut path = path(&[(0.0, 0.0), (24.0, 0.0), (24.0, 24.0)]);
+        path[1].radius = 6.0;
+        let commands = commands(&path);
+        assert_eq!(commands.len(), 3);
+        assert_eq!(commands[0], Command::Drive(18.0));
+        let Command::Arc { radius, angle } = commands[1].clone() else {
+            panic!("expected an arc, got {:?}", commands[1]);
+        };
+        assert!((radius - 6.0).abs() < 1e-4);
+        assert!((angle - 90.0).abs() < 1e-3);
+        assert_eq!(commands[2], Command::Drive(18.0));
+    }
+
+    #[test]
+    fn headings_turn_in_place_and_drop_fillets() {
+        let mut path = path(&[(0.0, 0.0), (24.0, 0.0), (24.0, 24.0)]);
+        path[0].heading = Some(-90.0);
+        path[1].radius = 6.0;
+        path[1].heading = Some(170.0);
+        // Start facing up, turn right to drive, then nearly face left before heading down
+        let turns = turns(&commands(&path));
+        assert_eq!(turns.len(), 3);
+        for (turn, expected) in turns.iter().zip([90.0, 170.0, -80.0]) {
+            assert!((turn - expected).abs() < 1e-3, "turned {turns:?}");
         }
-        addition.push_str(&format!("chassis.drive({});\n", format_num(length, 2)));
-        result.push_str(&addition);
     }
-    result
+
+    #[test]
+    fn reversed_points_drive_backwards() {
+        let mut path = path(&[(0.0, 0.0), (24.0, 0.0), (0.0, 0.0)]);
+        path[2].reverse = true;
+        assert_eq!(
+            commands(&path),
+            vec![Command::Drive(24.0), Command::Drive(-24.0)]
+        );
+    }
+
+    #[test]
+    fn speed_and_markers_follow_their_point() {
+        let mut path = path(&[(0.0, 0.0), (24.0, 0.0), (48.0, 0.0)]);
+        path[1].speed = Some(50.0);
+        path[1].markers = vec!["intake.spin();".into()];
+        assert_eq!(
+            commands(&path),
+            vec![
+                Command::Speed(50.0),
+                Command::Drive(24.0),
+                Command::Marker("intake.spin();".into()),
+                Command::Speed(100.0),
+                Command::Drive(24.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn start_markers_run_before_moving() {
+        let mut path = path(&[(0.0, 0.0), (24.0, 0.0)]);
+        path[0].markers = vec!["intake.spin();".into()];
+        assert_eq!(
+            commands(&path),
+            vec![
+                Command::Marker("intake.spin();".into()),
+                Command::Drive(24.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn templates_fill_placeholders() {
+        let mut path = path(&[(0.0, 0.0), (24.0, 0.0), (24.0, 24.0)]);
+        path[1].radius = 6.0;
+        let options = GenerateOptions {
+            templates: Templates {
+                header: "#include \"auton.h\"".into(),
+                wrapper: "void auton() {\n    {body}\n}".into(),
+                footer: "// end".into(),
+                drive: "moveTo({x}, {y}, {heading});".into(),
+                arc: "arc({radius}, {angle});".into(),
+                precision: 1,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let code = generate(&path, &Robot::default(), &options);
+        assert_eq!(
+            code,
+            "#include \"auton.h\"\n\nvoid auton() {\n    moveTo(18, 0, 0);\n    arc(6, 90);\n    moveTo(24, 24, 90);\n}\n\n// end\n"
+        );
+    }
+
+    #[test]
+    fn templates_start_facing_the_first_segment() {
+        let options = GenerateOptions {
+            templates: Templates {
+                drive: "moveTo({x}, {y}, {heading});".into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let code = generate(
+            &path(&[(24.0, 24.0), (24.0, 48.0)]),
+            &Robot::default(),
+            &options,
+        );
+        assert!(code.contains("moveTo(24, 48, 90);"), "generated {code}");
+    }
+
+    #[test]
+    fn drive_units_convert_through_the_wheel() {
+        let robot = Robot {
+            wheel_diameter: 4.0,
+            gear_ratio: 1.0,
+            ticks_per_rotation: 900.0,
+            ..Default::default()
+        };
+        let circumference = std::f32::consts::PI * 4.0;
+        assert_eq!(DriveUnit::Rotations.convert(circumference, &robot), 1.0);
+        assert_eq!(DriveUnit::Degrees.convert(circumference, &robot), 360.0);
+        assert_eq!(DriveUnit::Ticks.convert(circumference, &robot), 900.0);
+    }
 }
@@ -0,0 +1,229 @@
+use egui::{pos2, vec2, Pos2, Vec2};
+
+/// Turns smaller than this are ignored, in radians.
+pub const TURN_THRESHOLD: f32 = 0.5 * std::f32::consts::PI / 180.0;
+
+/// A piece of the path the robot actually drives.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Segment {
+    /// A straight line between two positions.
+    Line { start: Pos2, end: Pos2 },
+    /// A constant-curvature arc around a center.
+    /// `sweep` is positive when turning clockwise on screen (a right turn).
+    Arc {
+        center: Pos2,
+        radius: f32,
+        start_angle: f32,
+        sweep: f32,
+    },
+}
+
+impl Segment {
+    /// Length of the segment along the path.
+    pub fn length(&self) -> f32 {
+        match *self {
+            Segment::Line { start, end } => start.distance(end),
+            Segment::Arc { radius, sweep, .. } => radius * sweep.abs(),
+        }
+    }
+
+    /// Position along the segment, with `t` ranging from 0 to 1.
+    pub fn sample(&self, t: f32) -> Pos2 {
+        match *self {
+            Segment::Line { start, end } => start.lerp(end, t),
+            Segment::Arc {
+                center,
+                radius,
+                start_angle,
+                sweep,
+            } => center + Vec2::angled(start_angle + sweep * t) * radius,
+        }
+    }
+
+    /// Shortest distance from a position to the segment.
+    pub fn distance(&self, pos: Pos2) -> f32 {
+        match *self {
+            Segment::Line { start, end } => {
+                let line = end - start;
+                let t = if line.length_sq() > 0.0 {
+                    ((pos - start).dot(line) / line.length_sq()).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                pos.distance(start + line * t)
+            }
+            Segment::Arc {
+                center,
+                radius,
+                start_angle,
+                sweep,
+            } => {
+                // How far around the arc the position lies, in the direction of the sweep
+                let around = ((pos - center).angle() - start_angle) * sweep.signum();
+                if around.rem_euclid(std::f32::consts::TAU) <= sweep.abs() {
+                    (pos.distance(center) - radius).abs()
+                } else {
+                    pos.distance(self.sample(0.0))
+                        .min(pos.distance(self.sample(1.0)))
+                }
+            }
+        }
+    }
+
+    /// Same segment, with every position transformed.
+    /// Only valid for transforms that preserve angles (scaling and translation).
+    pub fn transform(&self, scale: f32, offset: Vec2) -> Segment {
+        match *self {
+            Segment::Line { start, end } => Segment::Line {
+                start: pos2(start.x * scale, start.y * scale) + offset,
+                end: pos2(end.x * scale, end.y * scale) + offset,
+            },
+            Segment::Arc {
+                center,
+                radius,
+                start_angle,
+                sweep,
+            } => Segment::Arc {
+                center: pos2(center.x * scale, center.y * scale) + offset,
+                radius: radius * scale,
+                start_angle,
+                sweep,
+            },
+        }
+    }
+}
+
+/// An evenly spaced sample along a path.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sample {
+    pub pos: Pos2,
+    /// Signed curvature, positive when curving clockwise
+    pub curvature: f32,
+    /// Distance along the path
+    pub distance: f32,
+}
+
+/// Wraps an angle into the range [-PI, PI).
+pub fn wrap_angle(angle: f32) -> f32 {
+    let pi = std::f32::consts::PI;
+    (angle + pi).rem_euclid(2.0 * pi) - pi
+}
+
+/// Splits a path of waypoints into the segments driven by the robot.
+///
+/// Each waypoint carries a fillet radius: a radius of zero keeps a sharp corner
+/// (turn in place), anything larger replaces the corner with an arc tangent to both sides.
+/// Fillets are shrunk if they would not fit on their neighbouring lines.
+///
+/// Every segment is returned alongside the index of the waypoint pair it belongs to,
+/// where pair `i` runs from waypoint `i` to waypoint `i + 1`.
+pub fn segments(path: &[(Pos2, f32)]) -> Vec<(usize, Segment)> {
+    let mut result = Vec::new();
+    if path.len() < 2 {
+        return result;
+    }
+    let mut start = path[0].0;
+    for i in 1..path.len() {
+        let (corner, radius) = path[i];
+        let fillet = path
+            .get(i + 1)
+            .and_then(|(next, _)| fillet(path[i - 1].0, corner, *next, radius));
+        match fillet {
+            Some((entry, exit, arc)) => {
+                result.push((i - 1, Segment::Line { start, end: entry }));
+                result.push((i - 1, arc));
+                start = exit;
+            }
+            None => {
+                result.push((i - 1, Segment::Line { start, end: corner }));
+                start = corner;
+            }
+        }
+    }
+    result
+}
+
+/// Computes the fillet replacing the corner `b` between lines `ab` and `bc`.
+/// Returns the entry point, exit point and the arc itself.
+fn fillet(a: Pos2, b: Pos2, c: Pos2, radius: f32) -> Option<(Pos2, Pos2, Segment)> {
+    if radius <= 0.0 {
+        return None;
+    }
+    let incoming = b - a;
+    let outgoing = c - b;
+    if incoming.length() <= f32::EPSILON || outgoing.length() <= f32::EPSILON {
+        return None;
+    }
+    let turn = wrap_angle(outgoing.angle() - incoming.angle());
+    let half = turn.abs() / 2.0;
+    if turn.abs() <= TURN_THRESHOLD || half >= std::f32::consts::FRAC_PI_2 - TURN_THRESHOLD {
+        return None;
+    }
+    // Each side gives up at most half its length so neighbouring fillets never overlap
+    let max_tangent = f32::min(incoming.length(), outgoing.length()) / 2.0;
+    let mut tangent = radius * half.tan();
+    let mut radius = radius;
+    if tangent > max_tangent {
+        tangent = max_tangent;
+        radius = tangent / half.tan();
+    }
+    let incoming = incoming.normalized();
+    let outgoing = outgoing.normalized();
+    let entry = b - incoming * tangent;
+    let exit = b + outgoing * tangent;
+    let center = entry + vec2(-incoming.y, incoming.x) * turn.signum() * radius;
+    Some((
+        entry,
+        exit,
+        Segment::Arc {
+            center,
+            radius,
+            start_angle: (entry - center).angle(),
+            sweep: turn,
+        },
+    ))
+}
+
+/// Samples a path at roughly even spacing, estimating the curvature at every sample.
+/// Sharp corners show up as a spike in curvature.
+pub fn sample(path: &[(Pos2, f32)], spacing: f32) -> Vec<Sample> {
+    let spacing = spacing.max(0.01);
+    let mut positions: Vec<Pos2> = Vec::new();
+    for (_, segment) in segments(path) {
+        let steps = (segment.length() / spacing).ceil() as usize;
+        for i in 0..steps {
+            positions.push(segment.sample(i as f32 / steps as f32));
+        }
+    }
+    if let Some((end, _)) = path.last() {
+        positions.push(*end);
+    }
+
+    let mut result: Vec<Sample> = Vec::with_capacity(positions.len());
+    for (i, &pos) in positions.iter().enumerate() {
+        let distance = result
+            .last()
+            .map_or(0.0, |prev| prev.distance + prev.pos.distance(pos));
+        let curvature = match (i.checked_sub(1), positions.get(i + 1)) {
+            (Some(prev), Some(&next)) => curvature(positions[prev], pos, next),
+            _ => 0.0,
+        };
+        result.push(Sample {
+            pos,
+            curvature,
+            distance,
+        });
+    }
+    result
+}
+
+/// Signed curvature of the circle through three points.
+fn curvature(a: Pos2, b: Pos2, c: Pos2) -> f32 {
+    let cross = (b - a).x * (c - b).y - (b - a).y * (c - b).x;
+    let product = a.distance(b) * b.distance(c) * c.distance(a);
+    if product <= f32::EPSILON {
+        0.0
+    } else {
+        2.0 * cross / product
+    }
+}
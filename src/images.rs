@@ -0,0 +1,365 @@
+use std::collections::BTreeMap;
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+
+use crate::field::{Field, FieldImage};
+
+/// Longest side of a stored image, in pixels.
+/// Comfortably more than the field is drawn or exported at.
+const MAX_SIZE: u32 = 1600;
+
+/// Uploaded field images, kept by name apart from the rest of the app's state:
+/// in IndexedDB on the web, or as files next to the app's state natively.
+#[derive(Default)]
+pub struct ImageStore {
+    images: BTreeMap<String, Arc<[u8]>>,
+    /// Whether added images are saved, rather than only kept for this session
+    persistent: bool,
+    /// Images still arriving from the browser's database
+    loading: Option<Receiver<(String, Vec<u8>)>>,
+}
+
+impl ImageStore {
+    /// Opens the saved images. On the web they arrive over the next few frames.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn open(_ctx: &egui::Context) -> Self {
+        let mut images = BTreeMap::new();
+        let entries = folder().and_then(|folder| std::fs::read_dir(folder).ok());
+        for path in entries.into_iter().flatten().flatten().map(|e| e.path()) {
+            let name = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .map(str::to_string);
+            if let (Some(name), Ok(bytes)) = (name, std::fs::read(&path)) {
+                images.insert(name, bytes.into());
+            }
+        }
+        Self {
+            images,
+            persistent: true,
+            loading: None,
+        }
+    }
+
+    /// Opens the saved images. On the web they arrive over the next few frames.
+    #[cfg(target_arch = "wasm32")]
+    pub fn open(ctx: &egui::Context) -> Self {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        indexed_db::load_all(sender, ctx.clone());
+        Self {
+            images: BTreeMap::new(),
+            persistent: true,
+            loading: Some(receiver),
+        }
+    }
+
+    /// Takes in any images that finished loading, returning whether there were some.
+    pub fn poll(&mut self) -> bool {
+        let Some(loading) = &self.loading else {
+            return false;
+        };
+        let mut loaded = false;
+        for (name, bytes) in loading.try_iter() {
+            self.images.insert(name, bytes.into());
+            loaded = true;
+        }
+        loaded
+    }
+
+    /// Names of every stored image, in order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.images.keys().map(String::as_str)
+    }
+
+    /// Encoded data of a stored image.
+    pub fn get(&self, name: &str) -> Option<Arc<[u8]>> {
+        self.images.get(name).cloned()
+    }
+
+    /// A copy of the field holding its image's data, for drawing without the store.
+    /// A stored image that's missing is left out.
+    pub fn resolve(&self, field: &Field) -> Field {
+        let mut field = field.clone();
+        if let FieldImage::Stored(name) = &field.image {
+            field.image = self.get(name).map_or(FieldImage::None, FieldImage::Custom);
+        }
+        field
+    }
+
+    /// Stores an image under a name based on `name`, shrinking and compressing it first.
+    /// Returns the name it was stored under, which differs if the name was taken.
+    pub fn add(&mut self, name: &str, bytes: &[u8]) -> Result<String, String> {
+        let bytes: Arc<[u8]> = compress(bytes)?.into();
+        let base = clean_name(name);
+        let mut name = base.clone();
+        let mut copy = 1;
+        while let Some(existing) = self.images.get(&name) {
+            if *existing == bytes {
+                return Ok(name);
+            }
+            copy += 1;
+            name = format!("{base} {copy}");
+        }
+        self.images.insert(name.clone(), bytes.clone());
+        if self.persistent {
+            save(&name, bytes);
+        }
+        Ok(name)
+    }
+
+    /// Deletes a stored image.
+    pub fn remove(&mut self, name: &str) {
+        if self.images.remove(name).is_some() && self.persistent {
+            delete(name);
+        }
+    }
+}
+
+/// Shrinks an image to fit within [`MAX_SIZE`], then encodes it as JPEG,
+/// or as PNG if it has any transparency.
+fn compress(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    let image = image::load_from_memory(bytes).map_err(|e| format!("Failed to read image: {e}"))?;
+    let image = if image.width().max(image.height()) > MAX_SIZE {
+        image.resize(MAX_SIZE, MAX_SIZE, image::imageops::FilterType::Triangle)
+    } else {
+        image
+    };
+    let rgba = image.to_rgba8();
+    let mut out = Vec::new();
+    let mut cursor = std::io::Cursor::new(&mut out);
+    if rgba.pixels().any(|p| p[3] < 255) {
+        rgba.write_to(&mut cursor, image::ImageFormat::Png)
+    } else {
+        image::DynamicImage::ImageRgba8(rgba)
+            .to_rgb8()
+            .write_with_encoder(image::codecs::jpeg::JpegEncoder::new_with_quality(
+                &mut cursor,
+                85,
+            ))
+    }
+    .map_err(|e| format!("Failed to compress image: {e}"))?;
+    Ok(out)
+}
+
+/// A name safe to use as a file name, from an uploaded file's name.
+fn clean_name(name: &str) -> String {
+    let stem = name.rsplit_once('.').map_or(name, |(stem, _)| stem);
+    let name: String = stem
+        .chars()
+        .filter(|c| c.is_alphanumeric() || matches!(c, ' ' | '-' | '_'))
+        .collect();
+    match name.trim() {
+        "" => "Image".to_string(),
+        name => name.to_string(),
+    }
+}
+
+/// Folder stored images are kept in, beside eframe's saved state.
+#[cfg(not(target_arch = "wasm32"))]
+fn folder() -> Option<std::path::PathBuf> {
+    eframe::storage_dir("eframe template").map(|dir| dir.join("images"))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn save(name: &str, bytes: Arc<[u8]>) {
+    let extension = match image::guess_format(&bytes) {
+        Ok(image::ImageFormat::Jpeg) => "jpg",
+        _ => "png",
+    };
+    let result = folder()
+        .ok_or("No storage folder".to_string())
+        .and_then(|folder| {
+            std::fs::create_dir_all(&folder).map_err(|e| e.to_string())?;
+            std::fs::write(folder.join(format!("{name}.{extension}")), &bytes)
+                .map_err(|e| e.to_string())
+        });
+    if let Err(e) = result {
+        log::warn!("Failed to save image {name}: {e}");
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn delete(name: &str) {
+    if let Some(folder) = folder() {
+        for extension in ["jpg", "png"] {
+            std::fs::remove_file(folder.join(format!("{name}.{extension}"))).ok();
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn save(name: &str, bytes: Arc<[u8]>) {
+    indexed_db::put(name.to_string(), bytes);
+}
+
+#[cfg(target_arch = "wasm32")]
+fn delete(name: &str) {
+    indexed_db::delete(name.to_string());
+}
+
+/// Images kept in the browser's IndexedDB, keyed by name.
+#[cfg(target_arch = "wasm32")]
+mod indexed_db {
+    use std::sync::mpsc::Sender;
+    use std::sync::Arc;
+
+    use eframe::wasm_bindgen::{closure::Closure, JsCast as _, JsValue};
+    use web_sys::{IdbCursorWithValue, IdbDatabase, IdbTransactionMode};
+
+    const DATABASE: &str = "pathy";
+    const STORE: &str = "images";
+
+    /// Opens the database, creating the image store the first time, then uses it.
+    fn open(then: impl FnOnce(IdbDatabase) -> Result<(), JsValue> + 'static) {
+        let result = (|| -> Result<(), JsValue> {
+            let request = web_sys::window()
+                .ok_or("No window")?
+                .indexed_db()?
+                .ok_or("No IndexedDB")?
+                .open_with_u32(DATABASE, 1)?;
+            let upgrading = request.clone();
+            let upgrade = Closure::once_into_js(move |_: web_sys::Event| {
+                let created = upgrading
+                    .result()
+                    .and_then(|db| db.dyn_into::<IdbDatabase>())
+                    .and_then(|db| db.create_object_store(STORE));
+                if let Err(e) = created {
+                    log::warn!("Failed to create image store: {e:?}");
+                }
+            });
+            request.set_onupgradeneeded(Some(upgrade.unchecked_ref()));
+            let opened = request.clone();
+            let success = Closure::once_into_js(move |_: web_sys::Event| {
+                let result = opened
+                    .result()
+                    .and_then(|db| db.dyn_into::<IdbDatabase>())
+                    .and_then(then);
+                if let Err(e) = result {
+                    log::warn!("Failed to use image store: {e:?}");
+                }
+            });
+            request.set_onsuccess(Some(success.unchecked_ref()));
+            Ok(())
+        })();
+        if let Err(e) = result {
+            log::warn!("Failed to open image store: {e:?}");
+        }
+    }
+
+    /// Sends every stored image, repainting as each arrives.
+    pub fn load_all(sender: Sender<(String, Vec<u8>)>, ctx: egui::Context) {
+        open(move |db| {
+            let request = db
+                .transaction_with_str(STORE)?
+                .object_store(STORE)?
+                .open_cursor()?;
+            let reading = request.clone();
+            // Called once per image, then once more with no cursor
+            let next = Closure::<dyn FnMut(web_sys::Event)>::new(move |_: web_sys::Event| {
+                let Ok(cursor) = reading
+                    .result()
+                    .and_then(|c| c.dyn_into::<IdbCursorWithValue>())
+                else {
+                    return;
+                };
+                if let (Some(name), Ok(value)) = (
+                    cursor.key().ok().and_then(|k| k.as_string()),
+                    cursor.value(),
+                ) {
+                    sender
+                        .send((name, js_sys::Uint8Array::new(&value).to_vec()))
+                        .ok();
+                    ctx.request_repaint();
+                }
+                cursor.continue_().ok();
+            })
+            .into_js_value();
+            request.set_onsuccess(Some(next.unchecked_ref()));
+            Ok(())
+        });
+    }
+
+    pub fn put(name: String, bytes: Arc<[u8]>) {
+        open(move |db| {
+            db.transaction_with_str_and_mode(STORE, IdbTransactionMode::Readwrite)?
+                .object_store(STORE)?
+                .put_with_key(
+                    &js_sys::Uint8Array::from(&bytes[..]),
+                    &JsValue::from_str(&name),
+                )?;
+            Ok(())
+        });
+    }
+
+    pub fn delete(name: String) {
+        open(move |db| {
+            db.transaction_with_str_and_mode(STORE, IdbTransactionMode::Readwrite)?
+                .object_store(STORE)?
+                .delete(&JsValue::from_str(&name))?;
+            Ok(())
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    fn png(width: u32, height: u32, alpha: u8) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        RgbaImage::from_pixel(width, height, Rgba([10, 120, 40, alpha]))
+            .write_to(
+                &mut std::io::Cursor::new(&mut bytes),
+                image::ImageFormat::Png,
+            )
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn images_are_shrunk_and_compressed() {
+        let photo = compress(&png(3200, 2000, 255)).unwrap();
+        assert_eq!(
+            image::guess_format(&photo).unwrap(),
+            image::ImageFormat::Jpeg
+        );
+        let photo = image::load_from_memory(&photo).unwrap();
+        assert_eq!((photo.width(), photo.height()), (1600, 1000));
+        // Transparency survives
+        let overlay = compress(&png(100, 100, 128)).unwrap();
+        assert_eq!(
+            image::guess_format(&overlay).unwrap(),
+            image::ImageFormat::Png
+        );
+    }
+
+    #[test]
+    fn names_are_cleaned_and_kept_apart() {
+        let mut store = ImageStore::default();
+        assert_eq!(
+            store.add("field/photo.jpeg", &png(4, 4, 255)).unwrap(),
+            "fieldphoto"
+        );
+        // The same image isn't stored twice, while another one gets its own name
+        assert_eq!(
+            store.add("field/photo.jpeg", &png(4, 4, 255)).unwrap(),
+            "fieldphoto"
+        );
+        assert_eq!(
+            store.add("fieldphoto.png", &png(8, 8, 255)).unwrap(),
+            "fieldphoto 2"
+        );
+        assert_eq!(store.add("...", &png(4, 4, 255)).unwrap(), "Image");
+        assert_eq!(store.names().count(), 3);
+
+        let field = Field {
+            image: FieldImage::Stored("fieldphoto 2".into()),
+            ..Default::default()
+        };
+        assert!(store.resolve(&field).image.bytes().is_some());
+        store.remove("fieldphoto 2");
+        assert_eq!(store.resolve(&field).image, FieldImage::None);
+        assert!(store.add("broken", b"not an image").is_err());
+    }
+}
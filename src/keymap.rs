@@ -0,0 +1,330 @@
+use std::collections::BTreeMap;
+
+use egui::{Key, KeyboardShortcut, Modifiers};
+
+/// Something the user can do from a shortcut or the command palette.
+#[derive(
+    serde::Deserialize, serde::Serialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord,
+)]
+pub enum Action {
+    Create,
+    Insert,
+    Delete,
+    Trim,
+    Measure,
+    Generate,
+    Clear,
+    Mirror,
+    ExportPng,
+    ExportSvg,
+    ExportReport,
+    Undo,
+    Redo,
+    Palette,
+    NudgeLeft,
+    NudgeRight,
+    NudgeUp,
+    NudgeDown,
+    NudgeLeftFine,
+    NudgeRightFine,
+    NudgeUpFine,
+    NudgeDownFine,
+    NudgeLeftCoarse,
+    NudgeRightCoarse,
+    NudgeUpCoarse,
+    NudgeDownCoarse,
+    NextPoint,
+    PreviousPoint,
+    DeletePoint,
+    Cancel,
+}
+
+impl Action {
+    /// Every action, in the order they're listed.
+    pub const ALL: [Action; 30] = [
+        Action::Create,
+        Action::Insert,
+        Action::Delete,
+        Action::Trim,
+        Action::Measure,
+        Action::Generate,
+        Action::Clear,
+        Action::Mirror,
+        Action::ExportPng,
+        Action::ExportSvg,
+        Action::ExportReport,
+        Action::Undo,
+        Action::Redo,
+        Action::Palette,
+        Action::NudgeLeft,
+        Action::NudgeRight,
+        Action::NudgeUp,
+        Action::NudgeDown,
+        Action::NudgeLeftFine,
+        Action::NudgeRightFine,
+        Action::NudgeUpFine,
+        Action::NudgeDownFine,
+        Action::NudgeLeftCoarse,
+        Action::NudgeRightCoarse,
+        Action::NudgeUpCoarse,
+        Action::NudgeDownCoarse,
+        Action::NextPoint,
+        Action::PreviousPoint,
+        Action::DeletePoint,
+        Action::Cancel,
+    ];
+
+    /// Name shown in the palette and the shortcut list.
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Create => "Create Mode",
+            Action::Insert => "Insert Mode",
+            Action::Delete => "Delete Mode",
+            Action::Trim => "Trim Mode",
+            Action::Measure => "Measure Mode",
+            Action::Generate => "Generate",
+            Action::Clear => "Clear Path",
+            Action::Mirror => "Mirror Path",
+            Action::ExportPng => "Export PNG",
+            Action::ExportSvg => "Export SVG",
+            Action::ExportReport => "Export Report",
+            Action::Undo => "Undo",
+            Action::Redo => "Redo",
+            Action::Palette => "Command Palette",
+            Action::NudgeLeft => "Nudge Left",
+            Action::NudgeRight => "Nudge Right",
+            Action::NudgeUp => "Nudge Up",
+            Action::NudgeDown => "Nudge Down",
+            Action::NudgeLeftFine => "Nudge Left (Fine)",
+            Action::NudgeRightFine => "Nudge Right (Fine)",
+            Action::NudgeUpFine => "Nudge Up (Fine)",
+            Action::NudgeDownFine => "Nudge Down (Fine)",
+            Action::NudgeLeftCoarse => "Nudge Left (Coarse)",
+            Action::NudgeRightCoarse => "Nudge Right (Coarse)",
+            Action::NudgeUpCoarse => "Nudge Up (Coarse)",
+            Action::NudgeDownCoarse => "Nudge Down (Coarse)",
+            Action::NextPoint => "Next Point",
+            Action::PreviousPoint => "Previous Point",
+            Action::DeletePoint => "Delete Point",
+            Action::Cancel => "Leave Mode",
+        }
+    }
+
+    /// What the action does, shown when hovering it.
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::Create => "Create new point",
+            Action::Insert => "Insert point in path",
+            Action::Delete => "Delete a single point",
+            Action::Trim => "Trim path to point",
+            Action::Measure => "Measure between two locations",
+            Action::Generate => "Generate path code",
+            Action::Clear => "Clear path",
+            Action::Mirror => "Flip the path to the other side of the field",
+            Action::ExportPng => "Save a picture of the field and path",
+            Action::ExportSvg => "Save a scalable picture with the field image embedded",
+            Action::ExportReport => "Notebook report with the field, waypoints, commands and times",
+            Action::Undo => "Undo the last change to the path",
+            Action::Redo => "Redo the last undone change",
+            Action::Palette => "Search for a command to run",
+            Action::NudgeLeft => "Move the inspected point left an inch",
+            Action::NudgeRight => "Move the inspected point right an inch",
+            Action::NudgeUp => "Move the inspected point up an inch",
+            Action::NudgeDown => "Move the inspected point down an inch",
+            Action::NudgeLeftFine => "Move the inspected point left a tenth of an inch",
+            Action::NudgeRightFine => "Move the inspected point right a tenth of an inch",
+            Action::NudgeUpFine => "Move the inspected point up a tenth of an inch",
+            Action::NudgeDownFine => "Move the inspected point down a tenth of an inch",
+            Action::NudgeLeftCoarse => "Move the inspected point left six inches",
+            Action::NudgeRightCoarse => "Move the inspected point right six inches",
+            Action::NudgeUpCoarse => "Move the inspected point up six inches",
+            Action::NudgeDownCoarse => "Move the inspected point down six inches",
+            Action::NextPoint => "Inspect the next waypoint",
+            Action::PreviousPoint => "Inspect the previous waypoint",
+            Action::DeletePoint => "Remove the inspected point",
+            Action::Cancel => "Leave the current mode",
+        }
+    }
+
+    /// Shortcut the action starts out with.
+    pub fn default_shortcut(self) -> Option<KeyboardShortcut> {
+        let plain = |key| Some(KeyboardShortcut::new(Modifiers::NONE, key));
+        let command = |key| Some(KeyboardShortcut::new(Modifiers::COMMAND, key));
+        let shift = |key| Some(KeyboardShortcut::new(Modifiers::SHIFT, key));
+        let alt = |key| Some(KeyboardShortcut::new(Modifiers::ALT, key));
+        match self {
+            Action::Create => plain(Key::C),
+            Action::Insert => plain(Key::I),
+            Action::Delete => plain(Key::D),
+            Action::Trim => plain(Key::T),
+            Action::Measure => plain(Key::M),
+            Action::Generate => plain(Key::G),
+            Action::ExportPng => command(Key::E),
+            Action::Undo => command(Key::Z),
+            Action::Redo => Some(KeyboardShortcut::new(
+                Modifiers::COMMAND | Modifiers::SHIFT,
+                Key::Z,
+            )),
+            Action::Palette => command(Key::K),
+            Action::NudgeLeft => plain(Key::ArrowLeft),
+            Action::NudgeRight => plain(Key::ArrowRight),
+            Action::NudgeUp => plain(Key::ArrowUp),
+            Action::NudgeDown => plain(Key::ArrowDown),
+            Action::NudgeLeftFine => alt(Key::ArrowLeft),
+            Action::NudgeRightFine => alt(Key::ArrowRight),
+            Action::NudgeUpFine => alt(Key::ArrowUp),
+            Action::NudgeDownFine => alt(Key::ArrowDown),
+            Action::NudgeLeftCoarse => shift(Key::ArrowLeft),
+            Action::NudgeRightCoarse => shift(Key::ArrowRight),
+            Action::NudgeUpCoarse => shift(Key::ArrowUp),
+            Action::NudgeDownCoarse => shift(Key::ArrowDown),
+            Action::NextPoint => plain(Key::Tab),
+            Action::PreviousPoint => shift(Key::Tab),
+            Action::DeletePoint => plain(Key::Delete),
+            Action::Cancel => plain(Key::Escape),
+            Action::Clear | Action::Mirror | Action::ExportSvg | Action::ExportReport => None,
+        }
+    }
+}
+
+/// Shortcuts bound to each action.
+///
+/// Only actions rebound by the user are stored, so actions added later start out with their default.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, Default, PartialEq)]
+pub struct Keymap {
+    /// Actions rebound by the user, with `None` for ones left unbound
+    bindings: BTreeMap<Action, Option<KeyboardShortcut>>,
+}
+
+impl Keymap {
+    /// Shortcut currently bound to an action.
+    pub fn shortcut(&self, action: Action) -> Option<KeyboardShortcut> {
+        match self.bindings.get(&action) {
+            Some(shortcut) => *shortcut,
+            None => action.default_shortcut(),
+        }
+    }
+
+    /// Binds an action to a shortcut, or unbinds it with `None`.
+    /// Any other action using the same shortcut is unbound, so a key always does one thing.
+    pub fn bind(&mut self, action: Action, shortcut: Option<KeyboardShortcut>) {
+        if shortcut.is_some() {
+            for other in Action::ALL {
+                if other != action && self.shortcut(other) == shortcut {
+                    self.bindings.insert(other, None);
+                }
+            }
+        }
+        self.bindings.insert(action, shortcut);
+    }
+
+    /// Goes back to every action's default shortcut.
+    pub fn reset(&mut self) {
+        self.bindings.clear();
+    }
+
+    /// Actions whose shortcuts were pressed, in order, taking the key presses out of the events.
+    /// Keys not bound to anything are left for egui.
+    pub fn pressed(&self, events: &mut Vec<egui::Event>) -> Vec<Action> {
+        let mut shortcuts: Vec<(Action, KeyboardShortcut)> = Action::ALL
+            .into_iter()
+            .filter_map(|action| Some((action, self.shortcut(action)?)))
+            .collect();
+        // Shortcuts with more modifiers go first, so Ctrl+Z isn't also read as Z
+        shortcuts.sort_by_key(|(_, shortcut)| {
+            let modifiers = shortcut.modifiers;
+            std::cmp::Reverse(
+                [
+                    modifiers.alt,
+                    modifiers.shift,
+                    modifiers.ctrl || modifiers.command || modifiers.mac_cmd,
+                ]
+                .into_iter()
+                .filter(|m| *m)
+                .count(),
+            )
+        });
+        let mut actions = Vec::new();
+        events.retain(|event| {
+            let egui::Event::Key {
+                key,
+                pressed: true,
+                modifiers,
+                ..
+            } = *event
+            else {
+                return true;
+            };
+            let action = shortcuts.iter().find(|(_, shortcut)| {
+                shortcut.logical_key == key && modifiers.matches_logically(shortcut.modifiers)
+            });
+            match action {
+                Some(&(action, _)) => {
+                    actions.push(action);
+                    false
+                }
+                None => true,
+            }
+        });
+        actions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rebinding_takes_the_shortcut_from_other_actions() {
+        let mut keymap = Keymap::default();
+        let g = KeyboardShortcut::new(Modifiers::NONE, Key::G);
+        assert_eq!(keymap.shortcut(Action::Generate), Some(g));
+
+        keymap.bind(Action::Mirror, Some(g));
+        assert_eq!(keymap.shortcut(Action::Mirror), Some(g));
+        assert_eq!(keymap.shortcut(Action::Generate), None);
+
+        // Unbound actions stay unbound after saving
+        let saved = serde_json::to_string(&keymap).unwrap();
+        let loaded: Keymap = serde_json::from_str(&saved).unwrap();
+        assert_eq!(loaded, keymap);
+
+        keymap.reset();
+        assert_eq!(keymap.shortcut(Action::Generate), Some(g));
+        assert_eq!(keymap.shortcut(Action::Mirror), None);
+    }
+
+    fn key(key: Key, modifiers: Modifiers) -> egui::Event {
+        egui::Event::Key {
+            key,
+            physical_key: None,
+            pressed: true,
+            repeat: false,
+            modifiers,
+        }
+    }
+
+    #[test]
+    fn pressed_prefers_the_most_specific_shortcut() {
+        let mut keymap = Keymap::default();
+        let mut events = vec![
+            key(Key::ArrowLeft, Modifiers::SHIFT),
+            key(Key::Tab, Modifiers::NONE),
+            key(Key::Q, Modifiers::NONE),
+        ];
+        assert_eq!(
+            keymap.pressed(&mut events),
+            vec![Action::NudgeLeftCoarse, Action::NextPoint]
+        );
+        // Unbound keys are left for egui
+        assert_eq!(events, vec![key(Key::Q, Modifiers::NONE)]);
+
+        keymap.bind(
+            Action::Generate,
+            Some(KeyboardShortcut::new(Modifiers::NONE, Key::Tab)),
+        );
+        let mut events = vec![key(Key::Tab, Modifiers::NONE)];
+        assert_eq!(keymap.pressed(&mut events), vec![Action::Generate]);
+        assert!(events.is_empty());
+    }
+}
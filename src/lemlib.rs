@@ -0,0 +1,244 @@
+use egui::{pos2, Pos2};
+use serde_json::{json, Value};
+
+use crate::generate::waypoints;
+use crate::path::Path;
+use crate::point::Point;
+use crate::pursuit::{sample, PursuitOptions};
+use crate::robot::Robot;
+
+/// Marks the end of the sampled points in a LemLib path file.
+const END_DATA: &str = "endData";
+/// Prefixes the path.jerryio project data appended to a LemLib path file.
+const JERRYIO_DATA: &str = "#PATH.JERRYIO-DATA";
+/// Millimeters per inch, the unit path.jerryio measures its units of length in.
+const MM_PER_INCH: f64 = 25.4;
+/// Sampled points further than this from the simplified path are kept, in inches.
+const SIMPLIFY_TOLERANCE: f32 = 0.5;
+/// Top speed in a LemLib path file.
+const MAX_SPEED: f32 = 127.0;
+
+/// Converts LemLib coordinates (inches from the field center, y up) into Pathy's.
+fn from_lemlib(x: f32, y: f32, field_size: f32) -> Point {
+    Point::new(field_size / 2.0 + x, field_size / 2.0 - y)
+}
+
+/// Converts Pathy coordinates into LemLib's.
+fn to_lemlib(pos: Pos2, field_size: f32) -> Pos2 {
+    pos2(pos.x - field_size / 2.0, field_size / 2.0 - pos.y)
+}
+
+/// Reads a LemLib path file, including those made by path.jerryio.
+///
+/// When path.jerryio project data is present its waypoints are used directly,
+/// otherwise the sampled points are simplified back into waypoints.
+pub fn import(text: &str, field_size: f32) -> Result<Path, String> {
+    if let Some((_, data)) = text.split_once(JERRYIO_DATA) {
+        let data: Value = serde_json::from_str(data.trim())
+            .map_err(|e| format!("Invalid path.jerryio data: {e}"))?;
+        return import_jerryio(&data, field_size);
+    }
+
+    let mut samples = Vec::new();
+    for line in text.lines().map(str::trim) {
+        if line == END_DATA {
+            break;
+        }
+        if line.is_empty() {
+            continue;
+        }
+        let mut values = line.split(',').map(|v| v.trim().parse::<f32>());
+        match (values.next(), values.next()) {
+            (Some(Ok(x)), Some(Ok(y))) => samples.push(pos2(x, y)),
+            _ => return Err(format!("Invalid LemLib point `{line}`")),
+        }
+    }
+    if samples.is_empty() {
+        return Err("No points found in LemLib path".into());
+    }
+    Ok(Path::new(
+        simplify(&samples, SIMPLIFY_TOLERANCE)
+            .into_iter()
+            .map(|p| from_lemlib(p.x, p.y, field_size))
+            .collect(),
+    ))
+}
+
+/// Reads the end points of the first path in path.jerryio project data.
+fn import_jerryio(data: &Value, field_size: f32) -> Result<Path, String> {
+    let scale = data["gc"]["uol"].as_f64().unwrap_or(MM_PER_INCH) / MM_PER_INCH;
+    let segments = data["paths"][0]["segments"]
+        .as_array()
+        .ok_or("No paths found in path.jerryio data")?;
+    let mut points = Vec::new();
+    for (i, segment) in segments.iter().enumerate() {
+        let controls = segment["controls"]
+            .as_array()
+            .ok_or("Invalid segment in path.jerryio data")?;
+        // Segments share end points, so only the first segment contributes its start
+        let ends = match (i, controls.first(), controls.last()) {
+            (0, Some(first), Some(last)) => vec![first, last],
+            (_, Some(_), Some(last)) => vec![last],
+            _ => return Err("Empty segment in path.jerryio data".into()),
+        };
+        for control in ends {
+            let (Some(x), Some(y)) = (control["x"].as_f64(), control["y"].as_f64()) else {
+                return Err("Invalid control point in path.jerryio data".into());
+            };
+            points.push(from_lemlib(
+                (x * scale) as f32,
+                (y * scale) as f32,
+                field_size,
+            ));
+        }
+    }
+    Ok(Path::new(points))
+}
+
+/// Removes points that lie within `tolerance` of the line through their neighbours,
+/// using the Ramer-Douglas-Peucker algorithm.
+fn simplify(points: &[Pos2], tolerance: f32) -> Vec<Pos2> {
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+    let (first, last) = (points[0], points[points.len() - 1]);
+    let line = last - first;
+    let distance = |p: Pos2| {
+        if line.length() <= f32::EPSILON {
+            p.distance(first)
+        } else {
+            ((p - first).x * line.y - (p - first).y * line.x).abs() / line.length()
+        }
+    };
+    let (idx, furthest) = points[1..points.len() - 1]
+        .iter()
+        .enumerate()
+        .map(|(i, &p)| (i + 1, distance(p)))
+        .fold(
+            (0, 0.0),
+            |best, item| if item.1 > best.1 { item } else { best },
+        );
+    if furthest <= tolerance {
+        return vec![first, last];
+    }
+    let mut result = simplify(&points[..=idx], tolerance);
+    result.pop();
+    result.extend(simplify(&points[idx..], tolerance));
+    result
+}
+
+/// Writes a path as a LemLib path file, with path.jerryio project data
+/// so the waypoints survive being opened in path.jerryio.
+pub fn export(path: &Path, robot: &Robot, options: &PursuitOptions, field_size: f32) -> String {
+    let mut result = String::new();
+    let max_velocity = robot.max_velocity().max(f32::EPSILON);
+    for point in sample(&waypoints(path.points()), robot, options) {
+        let pos = to_lemlib(point.pos, field_size);
+        let speed = (point.velocity / max_velocity * MAX_SPEED).clamp(0.0, MAX_SPEED);
+        result.push_str(&format!("{:.3}, {:.3}, {:.3}\n", pos.x, pos.y, speed));
+    }
+    result.push_str(END_DATA);
+    result.push('\n');
+
+    let control = |point: &Point| {
+        let pos = to_lemlib(pos2(point.x, point.y), field_size);
+        json!({
+            "uid": point.id.to_string(),
+            "x": pos.x,
+            "y": pos.y,
+            "lock": false,
+            "visible": true,
+            "heading": 0,
+            "__type": "end-point",
+        })
+    };
+    let segments: Vec<Value> = path
+        .points()
+        .windows(2)
+        .map(|pair| json!({ "controls": [control(&pair[0]), control(&pair[1])], "speed": [] }))
+        .collect();
+    let data = json!({
+        "format": "LemLib v0.4.x (inch, byte-voltage)",
+        "gc": {
+            "robotWidth": robot.width,
+            "robotHeight": robot.length,
+            "showRobot": false,
+            "uol": MM_PER_INCH,
+            "pointDensity": options.spacing,
+            "controlMagnetDistance": 5,
+        },
+        "paths": [{
+            "segments": segments,
+            "name": "Path",
+            "uid": uuid::Uuid::new_v4().to_string(),
+            "lock": false,
+            "visible": true,
+        }],
+    });
+    result.push_str(&format!("{JERRYIO_DATA} {data}\n"));
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIELD: f32 = 144.0;
+
+    fn positions(path: &Path) -> Vec<(f32, f32)> {
+        path.points()
+            .iter()
+            .map(|p| ((p.x * 100.0).round() / 100.0, (p.y * 100.0).round() / 100.0))
+            .collect()
+    }
+
+    #[test]
+    fn imports_lemlib_points_as_waypoints() {
+        // A right angle sampled every 6 inches, centered on the field
+        let text = "0, 0, 127\n6, 0, 127\n12, 0, 127\n12, -6, 100\n12, -12, 0\nendData\n200\n";
+        let path = import(text, FIELD).unwrap();
+        assert_eq!(
+            positions(&path),
+            vec![(72.0, 72.0), (84.0, 72.0), (84.0, 84.0)]
+        );
+    }
+
+    #[test]
+    fn imports_jerryio_data_in_other_units() {
+        let text = r#"0, 0, 0
+endData
+#PATH.JERRYIO-DATA {"gc": {"uol": 10}, "paths": [{"segments": [
+    {"controls": [{"x": 0, "y": 0}, {"x": 10, "y": 10}, {"x": 25.4, "y": 0}]},
+    {"controls": [{"x": 25.4, "y": 0}, {"x": 25.4, "y": 25.4}]}
+]}]}"#;
+        let path = import(text, FIELD).unwrap();
+        assert_eq!(
+            positions(&path),
+            vec![(72.0, 72.0), (82.0, 72.0), (82.0, 62.0)]
+        );
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        assert!(import("hello, world\nendData\n", FIELD).is_err());
+        assert!(import("", FIELD).is_err());
+    }
+
+    #[test]
+    fn export_round_trips() {
+        let path = Path::new(vec![
+            Point::new(20.0, 30.0),
+            Point::new(80.0, 30.0),
+            Point::new(80.0, 100.0),
+        ]);
+        let text = export(&path, &Robot::default(), &PursuitOptions::default(), FIELD);
+        assert_eq!(positions(&import(&text, FIELD).unwrap()), positions(&path));
+
+        // Without the project data, the samples still simplify to the same waypoints
+        let (samples, _) = text.split_once(JERRYIO_DATA).unwrap();
+        assert_eq!(
+            positions(&import(samples, FIELD).unwrap()),
+            positions(&path)
+        );
+    }
+}
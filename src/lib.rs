@@ -1,6 +1,46 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
 mod app;
-mod generate;
-mod point;
+/// Headless code generation for the command line.
+pub mod cli;
+/// Differences between two versions of a path.
+pub mod compare;
+mod draw;
+/// Field definitions: pictures, sizes, coordinates and obstacles.
+pub mod field;
+mod file;
+/// Turning paths into robot code.
+pub mod generate;
+/// Path geometry shared by drawing and every generator.
+pub mod geometry;
+mod images;
+mod keymap;
+/// Reading and writing LemLib and path.jerryio path files.
+pub mod lemlib;
+/// Reading chassis code back into paths.
+pub mod parse;
+/// Ordered collections of waypoints.
+pub mod path;
+/// Path waypoints.
+pub mod point;
+/// Saved paths and their settings.
+pub mod project;
+/// Sampled paths for pure pursuit.
+pub mod pursuit;
+/// Pictures of the field for exporting.
+pub mod render;
+/// Notebook reports describing a path.
+pub mod report;
+/// Robot descriptions.
+pub mod robot;
+/// Odometry logs of real runs, compared against the planned path.
+pub mod telemetry;
+/// Timed trajectories for Ramsete controllers.
+pub mod trajectory;
+
 pub use app::PathyApp;
+pub use generate::{generate, generate_path, Backend, Command, GenerateOptions};
+pub use path::Path;
+pub use point::Point;
+pub use project::Project;
+pub use robot::Robot;
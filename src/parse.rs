@@ -0,0 +1,236 @@
+use egui::{Pos2, Vec2};
+
+use crate::generate::{start_heading, GenerateOptions};
+use crate::geometry::wrap_angle;
+use crate::path::Path;
+use crate::point::Point;
+use crate::robot::Robot;
+
+/// Reads chassis code, like that generated by the chassis backend, back into a path.
+///
+/// The robot starts at the first point of `path`, facing its heading,
+/// or along its first segment if unset, the same way generated code assumes.
+/// Later points reuse the ids of `path` by position, so editing code keeps the drawing stable.
+/// `drive`, `turn`, `arc`, `tank` and `set_speed` calls move the robot;
+/// any other statement becomes a marker on the point the robot last reached,
+/// which is the start for statements before the first movement.
+pub fn parse(
+    code: &str,
+    path: &Path,
+    robot: &Robot,
+    options: &GenerateOptions,
+) -> Result<Path, String> {
+    let mut start = path
+        .points()
+        .first()
+        .cloned()
+        .unwrap_or_else(|| Point::new(0.0, 0.0));
+    start.markers.clear();
+    start.radius = 0.0;
+    let mut heading = start_heading(path.points()).unwrap_or(0.0);
+    let mut pos = Pos2::new(start.x, start.y);
+    let mut points = vec![start];
+    let mut speed = None;
+    // Whether the last point was placed by a drive, so an arc can round its corner
+    let mut driven = false;
+    // Whether the robot turned in place since reaching the last point
+    let mut turned = false;
+    let inches = |value: f32| options.drive_unit.to_inches(value, robot);
+
+    for (number, line) in code.lines().enumerate() {
+        let line = line.split("//").next().unwrap_or("").trim();
+        if line.is_empty() || !line.ends_with(';') {
+            continue;
+        }
+        let statement = line.trim_end_matches(';').trim();
+        let error = |message: &str| format!("Line {}: {message} `{line}`", number + 1);
+        let Some((name, args)) = call(statement) else {
+            points.last_mut().unwrap().markers.push(line.into());
+            continue;
+        };
+        let args: Vec<f32> = match args
+            .split(',')
+            .map(|arg| arg.trim().parse::<f32>())
+            .collect::<Result<_, _>>()
+        {
+            Ok(args) => args,
+            Err(_) => return Err(error("expected numbers in")),
+        };
+        match (name, args.as_slice()) {
+            ("drive", &[distance]) => {
+                let distance = inches(distance);
+                pos += Vec2::angled(heading) * distance;
+                let mut point = Point::new(pos.x, pos.y);
+                point.reverse = distance < 0.0;
+                point.speed = speed;
+                points.push(point);
+                driven = true;
+                turned = false;
+            }
+            ("turn", &[angle]) => {
+                heading += angle.to_radians();
+                turned = true;
+            }
+            ("arc", &[radius, angle]) => {
+                arc(&mut points, &mut pos, &mut heading, radius, angle, driven);
+                driven = false;
+            }
+            ("tank", &[left, right]) => {
+                let (left, right) = (inches(left), inches(right));
+                // The outside of a clockwise turn is the left side
+                let angle = (left - right) / robot.track_width;
+                if angle.abs() <= f32::EPSILON {
+                    return Err(error("straight tank movements aren't supported in"));
+                }
+                let radius = (left + right) / 2.0 / angle.abs();
+                arc(
+                    &mut points,
+                    &mut pos,
+                    &mut heading,
+                    radius,
+                    angle.to_degrees(),
+                    driven,
+                );
+                driven = false;
+            }
+            ("set_speed", &[percent]) => {
+                speed = (percent < 100.0).then_some(percent);
+            }
+            _ => return Err(error("unknown chassis call")),
+        }
+    }
+    if points.len() < 2 {
+        return Err("No drive or arc calls found".into());
+    }
+    // Turns after the last movement face the robot somewhere new
+    if turned {
+        points.last_mut().unwrap().heading = Some(wrap_angle(heading).to_degrees());
+    }
+
+    for (point, old) in points.iter_mut().zip(path.points()).skip(1) {
+        point.id = old.id;
+    }
+    Ok(Path::new(points))
+}
+
+/// Splits `chassis.name(args)` into its name and arguments.
+fn call(statement: &str) -> Option<(&str, &str)> {
+    let rest = statement.strip_prefix("chassis.")?;
+    let (name, args) = rest.split_once('(')?;
+    Some((name.trim(), args.strip_suffix(')')?))
+}
+
+/// Follows an arc from the robot's position, rounding the corner of the last point driven to.
+/// Arcs that don't follow a drive get a corner point of their own.
+fn arc(
+    points: &mut Vec<Point>,
+    pos: &mut Pos2,
+    heading: &mut f32,
+    radius: f32,
+    angle: f32,
+    driven: bool,
+) {
+    let sweep = angle.to_radians();
+    // The arc is a fillet between two lines meeting at a corner
+    let tangent = radius * (sweep.abs() / 2.0).tan();
+    let corner = *pos + Vec2::angled(*heading) * tangent;
+    if driven {
+        let last = points.last_mut().unwrap();
+        last.x = corner.x;
+        last.y = corner.y;
+        last.radius = radius;
+    } else {
+        let mut point = Point::new(corner.x, corner.y);
+        point.radius = radius;
+        points.push(point);
+    }
+    *heading += sweep;
+    *pos = corner + Vec2::angled(*heading) * tangent;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::{generate, ArcStyle, DriveUnit};
+
+    fn round_trip(options: &GenerateOptions) {
+        let mut corner = Point::new(48.0, 24.0);
+        corner.radius = 8.0;
+        corner.markers = vec!["intake.spin();".into()];
+        let mut end = Point::new(48.0, 72.0);
+        end.speed = Some(50.0);
+        end.heading = Some(180.0);
+        let path = Path::new(vec![Point::new(24.0, 24.0), corner, end]);
+        let robot = Robot::default();
+        let code = generate(path.points(), &robot, options);
+        let parsed = parse(&code, &path, &robot, options).unwrap();
+        assert_eq!(generate(parsed.points(), &robot, options), code);
+    }
+
+    #[test]
+    fn generated_code_round_trips() {
+        round_trip(&GenerateOptions::default());
+    }
+
+    #[test]
+    fn wheel_arcs_in_ticks_round_trip() {
+        round_trip(&GenerateOptions {
+            arc_style: ArcStyle::Wheels,
+            drive_unit: DriveUnit::Ticks,
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn paths_starting_off_the_x_axis_round_trip() {
+        let options = GenerateOptions::default();
+        let robot = Robot::default();
+        let path = Path::new(vec![
+            Point::new(24.0, 24.0),
+            Point::new(24.0, 48.0),
+            Point::new(48.0, 48.0),
+        ]);
+        let code = generate(path.points(), &robot, &options);
+        let parsed = parse(&code, &path, &robot, &options).unwrap();
+        assert_eq!(parsed.len(), path.len());
+        for (point, old) in parsed.points().iter().zip(path.points()) {
+            let distance = egui::pos2(point.x, point.y).distance(egui::pos2(old.x, old.y));
+            assert!(distance < 1e-3, "{point:?} moved from {old:?}");
+        }
+        assert_eq!(generate(parsed.points(), &robot, &options), code);
+    }
+
+    #[test]
+    fn hand_written_code_becomes_waypoints() {
+        let code = "void auton() {\n  chassis.drive(24); // forward\n  chassis.turn(-90);\n  chassis.drive(-12);\n  claw.open();\n}\n";
+        let start = Path::new(vec![Point::new(10.0, 50.0)]);
+        let path = parse(code, &start, &Robot::default(), &GenerateOptions::default()).unwrap();
+        let points = path.points();
+        assert_eq!(points.len(), 3);
+        assert_eq!((points[1].x, points[1].y), (34.0, 50.0));
+        assert!((points[2].y - 62.0).abs() < 1e-4 && points[2].reverse);
+        assert_eq!(points[2].markers, vec!["claw.open();".to_string()]);
+    }
+
+    #[test]
+    fn leading_statements_stay_on_the_start() {
+        let options = GenerateOptions::default();
+        let robot = Robot::default();
+        let code = "intake.spin();\nchassis.drive(24);\n";
+        let start = Path::new(vec![Point::new(0.0, 0.0)]);
+        let path = parse(code, &start, &robot, &options).unwrap();
+        assert_eq!(path.points()[0].markers, vec!["intake.spin();".to_string()]);
+        let generated = generate(path.points(), &robot, &options);
+        assert!(generated.ends_with(code), "generated {generated}");
+        let parsed = parse(&generated, &path, &robot, &options).unwrap();
+        assert_eq!(generate(parsed.points(), &robot, &options), generated);
+    }
+
+    #[test]
+    fn rejects_malformed_calls() {
+        let options = GenerateOptions::default();
+        let start = Path::default();
+        assert!(parse("chassis.drive(far);", &start, &Robot::default(), &options).is_err());
+        assert!(parse("chassis.fly(1);", &start, &Robot::default(), &options).is_err());
+    }
+}
@@ -0,0 +1,199 @@
+use uuid::Uuid;
+
+use crate::point::Point;
+
+/// A detour from the main path, driven when its condition holds.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct Branch {
+    /// Name of the function the branch is written as
+    pub name: String,
+    /// Code checked on reaching the branch, like `sensor.detects()`
+    pub condition: String,
+    /// Main path point the branch leaves from
+    pub from: Uuid,
+    /// Main path point the branch drives back to, if it returns.
+    /// Otherwise the run ends at the branch's last point.
+    pub rejoin: Option<Uuid>,
+    /// Points driven after leaving the main path
+    pub points: Vec<Point>,
+}
+
+impl Default for Branch {
+    fn default() -> Self {
+        Self {
+            name: "branch".into(),
+            condition: "true".into(),
+            from: Uuid::nil(),
+            rejoin: None,
+            points: Vec::new(),
+        }
+    }
+}
+
+/// How paths are saved: a plain list of points unless there are branches.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(untagged)]
+enum PathData {
+    Points(Vec<Point>),
+    Branched {
+        points: Vec<Point>,
+        branches: Vec<Branch>,
+    },
+}
+
+impl From<PathData> for Path {
+    fn from(data: PathData) -> Self {
+        match data {
+            PathData::Points(points) => Self::new(points),
+            PathData::Branched { points, branches } => Self { points, branches },
+        }
+    }
+}
+
+impl From<Path> for PathData {
+    fn from(path: Path) -> Self {
+        if path.branches.is_empty() {
+            PathData::Points(path.points)
+        } else {
+            PathData::Branched {
+                points: path.points,
+                branches: path.branches,
+            }
+        }
+    }
+}
+
+/// An ordered list of waypoints, addressed by their ids,
+/// along with branches leaving from them.
+///
+/// Points are only ever handed out as plain references, so the path can be shared
+/// between threads and edited without any runtime borrow checks.
+/// Serializes as a plain list of points when there are no branches.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, Default, PartialEq)]
+#[serde(from = "PathData", into = "PathData")]
+pub struct Path {
+    points: Vec<Point>,
+    branches: Vec<Branch>,
+}
+
+impl Path {
+    /// Creates a path through the points, in order.
+    pub fn new(points: Vec<Point>) -> Self {
+        Self {
+            points,
+            branches: Vec::new(),
+        }
+    }
+    /// All points of the main path, in order.
+    pub fn points(&self) -> &[Point] {
+        &self.points
+    }
+    /// Branches leaving the main path.
+    pub fn branches(&self) -> &[Branch] {
+        &self.branches
+    }
+    /// Branches leaving the main path, for editing.
+    /// Branches leaving from points no longer on the main path are dropped on the next removal.
+    pub fn branches_mut(&mut self) -> &mut Vec<Branch> {
+        &mut self.branches
+    }
+    /// Points of the main path followed by those of every branch.
+    pub fn all_points(&self) -> impl Iterator<Item = &Point> {
+        self.points
+            .iter()
+            .chain(self.branches.iter().flat_map(|b| &b.points))
+    }
+    /// Number of points.
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+    /// Whether the path has no points.
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+    /// Position of a point in the path.
+    pub fn index_of(&self, id: Uuid) -> Option<usize> {
+        self.points.iter().position(|p| p.id == id)
+    }
+    /// Gets a point by id, on the main path or a branch.
+    pub fn get(&self, id: Uuid) -> Option<&Point> {
+        self.all_points().find(|p| p.id == id)
+    }
+    /// Gets a point by id, on the main path or a branch, for editing.
+    pub fn get_mut(&mut self, id: Uuid) -> Option<&mut Point> {
+        self.points
+            .iter_mut()
+            .chain(self.branches.iter_mut().flat_map(|b| &mut b.points))
+            .find(|p| p.id == id)
+    }
+    /// Adds a point to the end of the path, returning its id.
+    pub fn push(&mut self, point: Point) -> Uuid {
+        let id = point.id;
+        self.points.push(point);
+        id
+    }
+    /// Inserts a point at a position in the path, returning its id.
+    pub fn insert(&mut self, index: usize, point: Point) -> Uuid {
+        let id = point.id;
+        self.points.insert(index.min(self.points.len()), point);
+        id
+    }
+    /// Removes a point from the main path or a branch, returning it if it was in the path.
+    pub fn remove(&mut self, id: Uuid) -> Option<Point> {
+        let removed = match self.index_of(id) {
+            Some(index) => self.points.remove(index),
+            None => self.branches.iter_mut().find_map(|branch| {
+                let index = branch.points.iter().position(|p| p.id == id)?;
+                Some(branch.points.remove(index))
+            })?,
+        };
+        self.prune();
+        Some(removed)
+    }
+    /// Removes a point along with every point after it.
+    pub fn trim(&mut self, id: Uuid) {
+        if let Some(index) = self.index_of(id) {
+            self.points.truncate(index);
+        }
+        for branch in &mut self.branches {
+            if let Some(index) = branch.points.iter().position(|p| p.id == id) {
+                branch.points.truncate(index);
+            }
+        }
+        self.prune();
+    }
+    /// Flips every point to the other side of a field `width` wide, left to right,
+    /// turning a path for one alliance into the other's.
+    pub fn mirror(&mut self, width: f32) {
+        self.points
+            .iter_mut()
+            .chain(self.branches.iter_mut().flat_map(|b| &mut b.points))
+            .for_each(|p| p.mirror(width));
+    }
+    /// Removes every point and branch.
+    pub fn clear(&mut self) {
+        self.points.clear();
+        self.branches.clear();
+    }
+    /// Drops branches leaving from points that are gone, and stops them rejoining at any.
+    fn prune(&mut self) {
+        let points = &self.points;
+        self.branches
+            .retain(|b| points.iter().any(|p| p.id == b.from));
+        for branch in &mut self.branches {
+            if branch
+                .rejoin
+                .is_some_and(|id| points.iter().all(|p| p.id != id))
+            {
+                branch.rejoin = None;
+            }
+        }
+    }
+}
+
+impl From<Vec<Point>> for Path {
+    fn from(points: Vec<Point>) -> Self {
+        Self::new(points)
+    }
+}
@@ -0,0 +1,49 @@
+use crate::generate::{generate_path, GenerateOptions};
+use crate::path::Path;
+use crate::point::Point;
+use crate::robot::Robot;
+
+/// A path along with everything needed to generate code from it.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct Project {
+    /// Name of the auton
+    pub name: String,
+    /// Path points
+    #[serde(alias = "points")]
+    pub path: Path,
+    /// Robot driving the path
+    pub robot: Robot,
+    /// Code generation settings
+    pub options: GenerateOptions,
+}
+
+impl Project {
+    /// Creates a project driving a path with the default robot and settings.
+    pub fn new(path: impl Into<Path>) -> Self {
+        Self {
+            path: path.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Generates code for the project with its own robot and settings.
+    pub fn generate(&self) -> String {
+        generate_path(&self.path, &self.robot, &self.options)
+    }
+
+    /// Serializes the project in the format read by [`Project::from_json`].
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("projects always serialize")
+    }
+
+    /// Parses a saved project,
+    /// also accepting the bare list of points saved by older versions.
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json).or_else(|err| {
+            serde_json::from_str::<Vec<Point>>(json)
+                .map(Self::new)
+                .map_err(|_| err)
+        })
+    }
+}
@@ -0,0 +1,82 @@
+use egui::Pos2;
+
+use crate::geometry;
+use crate::robot::Robot;
+
+/// Settings used to sample a path for pure pursuit.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct PursuitOptions {
+    /// Distance between samples, in inches
+    pub spacing: f32,
+    /// Deceleration available to the robot, in inches per second squared
+    pub max_acceleration: f32,
+    /// How much to slow down around curves, higher being faster
+    pub turn_constant: f32,
+    /// Lookahead distance, in inches
+    pub lookahead: f32,
+}
+
+impl Default for PursuitOptions {
+    fn default() -> Self {
+        Self {
+            spacing: 1.0,
+            max_acceleration: 80.0,
+            turn_constant: 3.0,
+            lookahead: 12.0,
+        }
+    }
+}
+
+/// A single sample of a pure pursuit path.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PursuitPoint {
+    pub pos: Pos2,
+    /// Signed curvature, positive when curving clockwise
+    pub curvature: f32,
+    /// Target velocity, in inches per second
+    pub velocity: f32,
+    /// Distance along the path, in inches
+    pub distance: f32,
+}
+
+/// Samples a path evenly, attaching curvature and target velocity to each point.
+pub fn sample(path: &[(Pos2, f32)], robot: &Robot, options: &PursuitOptions) -> Vec<PursuitPoint> {
+    let max_velocity = robot.max_velocity();
+    let mut result: Vec<PursuitPoint> = geometry::sample(path, options.spacing)
+        .into_iter()
+        .map(|s| PursuitPoint {
+            pos: s.pos,
+            curvature: s.curvature,
+            velocity: if s.curvature.abs() > f32::EPSILON {
+                f32::min(max_velocity, options.turn_constant / s.curvature.abs())
+            } else {
+                max_velocity
+            },
+            distance: s.distance,
+        })
+        .collect();
+
+    // Work backwards so the robot can always slow down in time
+    if let Some(last) = result.last_mut() {
+        last.velocity = 0.0;
+    }
+    for i in (0..result.len().saturating_sub(1)).rev() {
+        let gap = result[i + 1].distance - result[i].distance;
+        let reachable =
+            f32::sqrt(result[i + 1].velocity.powi(2) + 2.0 * options.max_acceleration * gap);
+        result[i].velocity = result[i].velocity.min(reachable);
+    }
+    result
+}
+
+/// Finds the point the robot steers towards from a sample of the path,
+/// being the first point further along the path that is at least the lookahead away.
+pub fn lookahead(path: &[PursuitPoint], from: usize, distance: f32) -> Option<Pos2> {
+    let robot = path.get(from)?.pos;
+    path[from..]
+        .iter()
+        .find(|p| p.pos.distance(robot) >= distance)
+        .or(path.last())
+        .map(|p| p.pos)
+}
@@ -0,0 +1,424 @@
+use egui::{pos2, vec2, Pos2, Vec2};
+use image::{Rgba, RgbaImage};
+
+use crate::field::Field;
+use crate::generate::waypoints;
+use crate::geometry::{segments, Segment};
+use crate::path::Path;
+use crate::robot::Robot;
+
+const PATH_COLOR: [u8; 4] = [255, 255, 0, 255];
+const POINT_COLOR: [u8; 4] = [255, 255, 0, 255];
+const MARKER_COLOR: [u8; 4] = [255, 165, 0, 255];
+const ROBOT_COLOR: [u8; 4] = [173, 216, 230, 255];
+const EMPTY_COLOR: [u8; 4] = [30, 30, 30, 255];
+
+/// Everything drawn in an exported picture of the field.
+pub struct Scene<'a> {
+    pub path: &'a Path,
+    pub robot: &'a Robot,
+    /// Field drawn under the path, its image lined up by its calibration
+    pub field: &'a Field,
+    /// Whether to draw the robot's footprint at every waypoint
+    pub footprints: bool,
+}
+
+impl Scene<'_> {
+    /// Corners of the robot at each waypoint, facing the way it drives off.
+    fn footprints(&self) -> Vec<[Pos2; 4]> {
+        if !self.footprints {
+            return Vec::new();
+        }
+        let points = self.path.points();
+        points
+            .iter()
+            .enumerate()
+            .map(|(i, point)| {
+                let pos = pos2(point.x, point.y);
+                let heading = match (points.get(i + 1), i.checked_sub(1)) {
+                    (Some(next), _) => pos2(next.x, next.y) - pos,
+                    (None, Some(prev)) => pos - pos2(points[prev].x, points[prev].y),
+                    _ => Vec2::X,
+                };
+                let forward = heading.normalized() * self.robot.length / 2.0;
+                let side = forward.rot90().normalized() * self.robot.width / 2.0;
+                [
+                    pos + forward + side,
+                    pos + forward - side,
+                    pos - forward - side,
+                    pos - forward + side,
+                ]
+            })
+            .collect()
+    }
+}
+
+impl Scene<'_> {
+    /// Pixel height of a picture `width` pixels wide, keeping the field's proportions.
+    fn height(&self, width: u32) -> u32 {
+        ((width as f32 * self.field.height / self.field.width).round() as u32).max(1)
+    }
+}
+
+/// Draws the field's image `width` by `height` pixels.
+/// A calibrated image is warped so its landmarks land on the field,
+/// and anything it doesn't cover is left empty.
+fn background(field: &Field, width: u32, height: u32) -> Result<RgbaImage, String> {
+    let Some(bytes) = field.image.bytes() else {
+        return Ok(RgbaImage::from_pixel(width, height, Rgba(EMPTY_COLOR)));
+    };
+    let source =
+        image::load_from_memory(bytes).map_err(|e| format!("Failed to read field image: {e}"))?;
+    let Some(transform) = field.image_transform() else {
+        return Ok(source
+            .resize_exact(width, height, image::imageops::FilterType::Triangle)
+            .to_rgba8());
+    };
+    let source = source.to_rgba8();
+    let ratio = width as f32 / field.width;
+    Ok(RgbaImage::from_fn(width, height, |x, y| {
+        let pixel = transform.apply(pos2(x as f32 + 0.5, y as f32 + 0.5) / ratio);
+        image::imageops::interpolate_bilinear(&source, pixel.x - 0.5, pixel.y - 0.5)
+            .unwrap_or(Rgba(EMPTY_COLOR))
+    }))
+}
+
+/// Encodes an image as PNG.
+fn encode_png(image: &RgbaImage) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    image
+        .write_to(
+            &mut std::io::Cursor::new(&mut bytes),
+            image::ImageFormat::Png,
+        )
+        .map_err(|e| format!("Failed to encode PNG: {e}"))?;
+    Ok(bytes)
+}
+
+/// Renders the scene to a PNG image, `size` pixels wide.
+pub fn render_png(scene: &Scene<'_>, size: u32) -> Result<Vec<u8>, String> {
+    let size = size.max(1);
+    let height = scene.height(size);
+    let mut image = background(scene.field, size, height)?;
+    let ratio = size as f32 / scene.field.width;
+    let scale = size as f32 / 720.0; // keep strokes the same weight as on screen
+    let to_image = |pos: Pos2| pos2(pos.x * ratio, pos.y * ratio);
+
+    for corners in scene.footprints() {
+        for i in 0..4 {
+            stroke_line(
+                &mut image,
+                to_image(corners[i]),
+                to_image(corners[(i + 1) % 4]),
+                1.5 * scale,
+                ROBOT_COLOR,
+            );
+        }
+    }
+    for (_, segment) in segments(&waypoints(scene.path.points())) {
+        let segment = segment.transform(ratio, Vec2::ZERO);
+        let steps = (segment.length() / 2.0).ceil().max(1.0) as usize;
+        for i in 0..steps {
+            stroke_line(
+                &mut image,
+                segment.sample(i as f32 / steps as f32),
+                segment.sample((i + 1) as f32 / steps as f32),
+                2.0 * scale,
+                PATH_COLOR,
+            );
+        }
+    }
+    for point in scene.path.points() {
+        let center = to_image(pos2(point.x, point.y));
+        stroke_circle(&mut image, center, 5.0 * scale, 2.0 * scale, POINT_COLOR);
+        if !point.markers.is_empty() {
+            fill_circle(&mut image, center, 2.5 * scale, MARKER_COLOR);
+        }
+    }
+    encode_png(&image)
+}
+
+/// Renders the scene to an SVG image, `size` pixels wide.
+/// The field image is embedded, so the file stands on its own.
+/// A calibrated image is embedded already warped, since SVG can't correct perspective.
+pub fn render_svg(scene: &Scene<'_>, size: u32) -> String {
+    let height = scene.height(size);
+    let ratio = size as f32 / scene.field.width;
+    let scale = size as f32 / 720.0;
+    let color = |c: [u8; 4]| format!("rgb({},{},{})", c[0], c[1], c[2]);
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{size}\" height=\"{height}\" viewBox=\"0 0 {size} {height}\">\n"
+    );
+    let warped = scene
+        .field
+        .image_transform()
+        .and_then(|_| background(scene.field, size, height).ok())
+        .and_then(|image| encode_png(&image).ok());
+    match warped.as_deref().or(scene.field.image.bytes()) {
+        Some(bytes) => svg.push_str(&format!(
+            "  <image width=\"{size}\" height=\"{height}\" preserveAspectRatio=\"none\" href=\"data:{};base64,{}\"/>\n",
+            mime_type(bytes),
+            base64(bytes)
+        )),
+        None => svg.push_str(&format!(
+            "  <rect width=\"{size}\" height=\"{height}\" fill=\"{}\"/>\n",
+            color(EMPTY_COLOR)
+        )),
+    }
+
+    for corners in scene.footprints() {
+        let corners: Vec<String> = corners
+            .iter()
+            .map(|p| format!("{:.2},{:.2}", p.x * ratio, p.y * ratio))
+            .collect();
+        svg.push_str(&format!(
+            "  <polygon points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"{:.2}\"/>\n",
+            corners.join(" "),
+            color(ROBOT_COLOR),
+            1.5 * scale
+        ));
+    }
+
+    let mut d = String::new();
+    for (i, (_, segment)) in segments(&waypoints(scene.path.points()))
+        .into_iter()
+        .enumerate()
+    {
+        let segment = segment.transform(ratio, Vec2::ZERO);
+        let start = segment.sample(0.0);
+        if i == 0 {
+            d.push_str(&format!("M {:.2} {:.2}", start.x, start.y));
+        }
+        let end = segment.sample(1.0);
+        match segment {
+            Segment::Line { .. } => d.push_str(&format!(" L {:.2} {:.2}", end.x, end.y)),
+            Segment::Arc { radius, sweep, .. } => d.push_str(&format!(
+                " A {radius:.2} {radius:.2} 0 0 {} {:.2} {:.2}",
+                // Screen coordinates point down, so a positive sweep is clockwise
+                u8::from(sweep > 0.0),
+                end.x,
+                end.y
+            )),
+        }
+    }
+    if !d.is_empty() {
+        svg.push_str(&format!(
+            "  <path d=\"{d}\" fill=\"none\" stroke=\"{}\" stroke-width=\"{:.2}\"/>\n",
+            color(PATH_COLOR),
+            2.0 * scale
+        ));
+    }
+
+    for point in scene.path.points() {
+        svg.push_str(&format!(
+            "  <circle cx=\"{:.2}\" cy=\"{:.2}\" r=\"{:.2}\" fill=\"none\" stroke=\"{}\" stroke-width=\"{:.2}\"/>\n",
+            point.x * ratio,
+            point.y * ratio,
+            5.0 * scale,
+            color(POINT_COLOR),
+            2.0 * scale
+        ));
+        if !point.markers.is_empty() {
+            svg.push_str(&format!(
+                "  <circle cx=\"{:.2}\" cy=\"{:.2}\" r=\"{:.2}\" fill=\"{}\"/>\n",
+                point.x * ratio,
+                point.y * ratio,
+                2.5 * scale,
+                color(MARKER_COLOR)
+            ));
+        }
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Blends a color onto a pixel, ignoring anything outside the image.
+fn blend(image: &mut RgbaImage, x: i64, y: i64, color: [u8; 4], coverage: f32) {
+    if x < 0 || y < 0 || x >= image.width() as i64 || y >= image.height() as i64 {
+        return;
+    }
+    let alpha = coverage.clamp(0.0, 1.0) * color[3] as f32 / 255.0;
+    let pixel = image.get_pixel_mut(x as u32, y as u32);
+    for c in 0..3 {
+        pixel[c] = (pixel[c] as f32 * (1.0 - alpha) + color[c] as f32 * alpha).round() as u8;
+    }
+    pixel[3] = pixel[3].max((alpha * 255.0) as u8);
+}
+
+/// Draws an antialiased line of a width.
+fn stroke_line(image: &mut RgbaImage, a: Pos2, b: Pos2, width: f32, color: [u8; 4]) {
+    let half = width / 2.0;
+    let min = a.min(b) - vec2(half + 1.0, half + 1.0);
+    let max = a.max(b) + vec2(half + 1.0, half + 1.0);
+    let line = b - a;
+    for y in min.y.floor() as i64..=max.y.ceil() as i64 {
+        for x in min.x.floor() as i64..=max.x.ceil() as i64 {
+            let p = pos2(x as f32 + 0.5, y as f32 + 0.5);
+            let t = if line.length_sq() > 0.0 {
+                ((p - a).dot(line) / line.length_sq()).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            let distance = p.distance(a + line * t);
+            blend(image, x, y, color, half + 0.5 - distance);
+        }
+    }
+}
+
+/// Draws an antialiased circle outline of a width.
+fn stroke_circle(image: &mut RgbaImage, center: Pos2, radius: f32, width: f32, color: [u8; 4]) {
+    let reach = radius + width;
+    for y in (center.y - reach).floor() as i64..=(center.y + reach).ceil() as i64 {
+        for x in (center.x - reach).floor() as i64..=(center.x + reach).ceil() as i64 {
+            let distance = (pos2(x as f32 + 0.5, y as f32 + 0.5).distance(center) - radius).abs();
+            blend(image, x, y, color, width / 2.0 + 0.5 - distance);
+        }
+    }
+}
+
+/// Draws an antialiased filled circle.
+fn fill_circle(image: &mut RgbaImage, center: Pos2, radius: f32, color: [u8; 4]) {
+    let reach = radius + 1.0;
+    for y in (center.y - reach).floor() as i64..=(center.y + reach).ceil() as i64 {
+        for x in (center.x - reach).floor() as i64..=(center.x + reach).ceil() as i64 {
+            let distance = pos2(x as f32 + 0.5, y as f32 + 0.5).distance(center);
+            blend(image, x, y, color, radius + 0.5 - distance);
+        }
+    }
+}
+
+/// Guesses the type of an encoded image from its contents.
+fn mime_type(bytes: &[u8]) -> &'static str {
+    match image::guess_format(bytes) {
+        Ok(image::ImageFormat::Jpeg) => "image/jpeg",
+        Ok(image::ImageFormat::WebP) => "image/webp",
+        _ => "image/png",
+    }
+}
+
+/// Encodes bytes as standard base64.
+pub fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut result = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                result.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                result.push('=');
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::field::{FieldImage, Landmark};
+    use crate::point::Point;
+
+    #[test]
+    fn base64_matches_reference() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn renders_at_requested_size() {
+        let mut end = Point::new(100.0, 50.0);
+        end.markers = vec!["intake.spin();".into()];
+        let path = Path::new(vec![Point::new(10.0, 10.0), end]);
+        let robot = Robot::default();
+        let field = Field {
+            image: FieldImage::None,
+            ..Default::default()
+        };
+        let scene = Scene {
+            path: &path,
+            robot: &robot,
+            field: &field,
+            footprints: true,
+        };
+        let png = render_png(&scene, 300).unwrap();
+        let image = image::load_from_memory(&png).unwrap();
+        assert_eq!((image.width(), image.height()), (300, 300));
+        // The first waypoint's ring passes just above its center
+        let center = pos2(10.0, 10.0).to_vec2() * (300.0 / 140.5);
+        let ring = image.to_rgba8();
+        let pixel = ring.get_pixel(center.x as u32, (center.y - 5.0 * 300.0 / 720.0) as u32);
+        assert_ne!(pixel.0, EMPTY_COLOR);
+        // The last waypoint runs a marker, so it has a dot in the middle
+        let center = pos2(100.0, 50.0).to_vec2() * (300.0 / 140.5);
+        assert_eq!(
+            ring.get_pixel(center.x as u32, center.y as u32).0,
+            MARKER_COLOR
+        );
+
+        let svg = render_svg(&scene, 300);
+        assert!(svg.starts_with("<svg"));
+        assert_eq!(svg.matches("<circle").count(), 3);
+        assert_eq!(svg.matches("fill=\"rgb(255,165,0)\"").count(), 1);
+        assert_eq!(svg.matches("<polygon").count(), 2);
+
+        // Fields that aren't square keep their proportions
+        let tall = Field {
+            width: 72.0,
+            height: 96.0,
+            ..field.clone()
+        };
+        let scene = Scene {
+            field: &tall,
+            ..scene
+        };
+        let png = render_png(&scene, 300).unwrap();
+        let image = image::load_from_memory(&png).unwrap();
+        assert_eq!((image.width(), image.height()), (300, 400));
+    }
+
+    #[test]
+    fn calibrated_images_are_warped() {
+        // A 20 by 10 photo: the field fills its left half, the right half is bright
+        let photo = RgbaImage::from_fn(20, 10, |x, _| {
+            Rgba(if x < 10 { [0, 0, 255, 255] } else { [255; 4] })
+        });
+        let field = Field {
+            width: 100.0,
+            height: 100.0,
+            image: FieldImage::Custom(encode_png(&photo).unwrap().into()),
+            calibration: vec![
+                Landmark {
+                    x: 0.0,
+                    y: 0.0,
+                    image_x: 0.0,
+                    image_y: 0.0,
+                },
+                Landmark {
+                    x: 100.0,
+                    y: 100.0,
+                    image_x: 10.0,
+                    image_y: 10.0,
+                },
+            ],
+            ..Default::default()
+        };
+        let image = background(&field, 50, 50).unwrap();
+        assert_eq!(image.get_pixel(45, 25).0, [0, 0, 255, 255]);
+        // Stretched, the bright half would cover the right of the field
+        let stretched = Field {
+            calibration: Vec::new(),
+            ..field
+        };
+        assert_eq!(
+            background(&stretched, 50, 50).unwrap().get_pixel(45, 25).0,
+            [255; 4]
+        );
+    }
+}
@@ -0,0 +1,244 @@
+use egui::pos2;
+
+use crate::generate::{commands, format_commands, format_num, Command, GenerateOptions};
+use crate::path::Path;
+use crate::render::base64;
+use crate::robot::Robot;
+
+/// Document format of a report.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReportFormat {
+    Markdown,
+    Html,
+}
+
+impl ReportFormat {
+    /// File extension for reports in this format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ReportFormat::Markdown => "md",
+            ReportFormat::Html => "html",
+        }
+    }
+}
+
+/// A table of text cells, written out in either format.
+struct Table {
+    header: Vec<&'static str>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    fn write(&self, format: ReportFormat, out: &mut String) {
+        match format {
+            ReportFormat::Markdown => {
+                out.push_str(&format!("| {} |\n", self.header.join(" | ")));
+                out.push_str(&format!("|{}\n", " --- |".repeat(self.header.len())));
+                for row in &self.rows {
+                    let row: Vec<String> = row.iter().map(|c| c.replace('|', "\\|")).collect();
+                    out.push_str(&format!("| {} |\n", row.join(" | ")));
+                }
+            }
+            ReportFormat::Html => {
+                out.push_str("<table>\n  <tr>");
+                for cell in &self.header {
+                    out.push_str(&format!("<th>{cell}</th>"));
+                }
+                out.push_str("</tr>\n");
+                for row in &self.rows {
+                    out.push_str("  <tr>");
+                    for cell in row {
+                        out.push_str(&format!("<td>{}</td>", escape_html(cell)));
+                    }
+                    out.push_str("</tr>\n");
+                }
+                out.push_str("</table>\n");
+            }
+        }
+    }
+}
+
+/// Escapes text for use inside HTML elements.
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Writes a report describing a path for an engineering notebook:
+/// a picture of the field, the waypoints and their markers,
+/// and every generated command with its estimated time.
+///
+/// `image` is a PNG of the field, usually from [`crate::render::render_png`],
+/// and is embedded so the report stands on its own.
+pub fn report(
+    path: &Path,
+    robot: &Robot,
+    options: &GenerateOptions,
+    image: Option<&[u8]>,
+    format: ReportFormat,
+) -> String {
+    let points = path.points();
+    let commands = commands(points);
+    let times = robot.command_times(&commands, options.trajectory.max_acceleration);
+    let total: f32 = times.iter().sum();
+
+    let code = |text: &str| match format {
+        ReportFormat::Markdown => format!("`{text}`"),
+        ReportFormat::Html => text.to_string(),
+    };
+    let waypoint_table = Table {
+        header: vec!["#", "X (in)", "Y (in)", "Heading (°)", "Markers"],
+        rows: points
+            .iter()
+            .enumerate()
+            .map(|(i, point)| {
+                let pos = pos2(point.x, point.y);
+                // Heading the robot leaves the waypoint with, or arrives at the last one with
+                let heading = match (points.get(i + 1), i.checked_sub(1)) {
+                    (Some(next), _) => Some(pos2(next.x, next.y) - pos),
+                    (None, Some(prev)) => Some(pos - pos2(points[prev].x, points[prev].y)),
+                    _ => None,
+                };
+                let frame = &options.frame;
+                let shown = frame.to_frame(pos);
+                vec![
+                    (i + 1).to_string(),
+                    format_num(shown.x, 2),
+                    format_num(shown.y, 2),
+                    heading.map_or("-".into(), |h| {
+                        format_num(frame.heading(h.angle().to_degrees()), 1)
+                    }),
+                    if point.markers.is_empty() {
+                        "-".into()
+                    } else {
+                        let markers: Vec<String> = point.markers.iter().map(|m| code(m)).collect();
+                        markers.join(" ")
+                    },
+                ]
+            })
+            .collect(),
+    };
+
+    let mut elapsed = 0.0;
+    let command_table = Table {
+        header: vec![
+            "#",
+            "Command",
+            "Distance (in)",
+            "Angle (°)",
+            "Time (s)",
+            "Elapsed (s)",
+        ],
+        rows: commands
+            .iter()
+            .zip(times)
+            .zip(format_commands(points, &commands, robot, options))
+            .enumerate()
+            .map(|(i, ((command, time), line))| {
+                let (distance, angle) = match *command {
+                    Command::Drive(length) => (Some(length), None),
+                    Command::Turn(angle) => (None, Some(angle)),
+                    Command::Arc { radius, angle } => {
+                        (Some(radius * angle.to_radians().abs()), Some(angle))
+                    }
+                    Command::Speed(_) | Command::Marker(_) => (None, None),
+                };
+                elapsed += time;
+                vec![
+                    (i + 1).to_string(),
+                    code(line.trim()),
+                    distance.map_or("-".into(), |d| format_num(d, 2)),
+                    angle.map_or("-".into(), |a| format_num(a, 2)),
+                    format_num(time, 2),
+                    format_num(elapsed, 2),
+                ]
+            })
+            .collect(),
+    };
+
+    let summary = format!(
+        "Robot: {}. {} waypoints, {} commands, about {:.1} s driving each command from rest to rest.",
+        robot.name,
+        points.len(),
+        commands.len(),
+        total
+    );
+    let mut out = String::new();
+    match format {
+        ReportFormat::Markdown => {
+            out.push_str("# Auton Report\n\n");
+            out.push_str(&summary);
+            out.push_str("\n\n");
+            if let Some(image) = image {
+                out.push_str(&format!(
+                    "![Field](data:image/png;base64,{})\n\n",
+                    base64(image)
+                ));
+            }
+            out.push_str("## Waypoints\n\n");
+            waypoint_table.write(format, &mut out);
+            out.push_str("\n## Commands\n\n");
+            command_table.write(format, &mut out);
+        }
+        ReportFormat::Html => {
+            out.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Auton Report</title>\n");
+            out.push_str("<style>table { border-collapse: collapse; } th, td { border: 1px solid #888; padding: 2px 8px; }</style>\n");
+            out.push_str("</head>\n<body>\n<h1>Auton Report</h1>\n");
+            out.push_str(&format!("<p>{}</p>\n", escape_html(&summary)));
+            if let Some(image) = image {
+                out.push_str(&format!(
+                    "<img alt=\"Field\" src=\"data:image/png;base64,{}\">\n",
+                    base64(image)
+                ));
+            }
+            out.push_str("<h2>Waypoints</h2>\n");
+            waypoint_table.write(format, &mut out);
+            out.push_str("<h2>Commands</h2>\n");
+            command_table.write(format, &mut out);
+            out.push_str("</body>\n</html>\n");
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::point::Point;
+
+    fn path() -> Path {
+        let mut end = Point::new(24.0, 24.0);
+        end.markers = vec!["intake.spin();".into()];
+        Path::new(vec![Point::new(0.0, 0.0), Point::new(24.0, 0.0), end])
+    }
+
+    #[test]
+    fn markdown_lists_waypoints_and_commands() {
+        let report = report(
+            &path(),
+            &Robot::default(),
+            &GenerateOptions::default(),
+            None,
+            ReportFormat::Markdown,
+        );
+        assert!(report.contains("| 1 | 0 | 0 | 0 | - |"));
+        assert!(report.contains("| 3 | 24 | 24 | 90 | `intake.spin();` |"));
+        assert!(report.contains("| 2 | `chassis.turn(90);` | - | 90 |"));
+        assert_eq!(report.matches("`chassis.").count(), 3);
+        assert!(report.contains("| 4 | `intake.spin();` | - | - | 0 |"));
+    }
+
+    #[test]
+    fn html_embeds_the_image() {
+        let report = report(
+            &path(),
+            &Robot::default(),
+            &GenerateOptions::default(),
+            Some(b"png"),
+            ReportFormat::Html,
+        );
+        assert!(report.contains("src=\"data:image/png;base64,cG5n\""));
+        assert_eq!(report.matches("<tr>").count(), 4 + 5);
+    }
+}
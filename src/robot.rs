@@ -0,0 +1,122 @@
+use crate::generate::Command;
+
+/// Layout of the drivetrain.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Drivetrain {
+    Tank,
+    Holonomic,
+    XDrive,
+}
+
+/// A saved description of a robot.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct Robot {
+    /// Profile name
+    pub name: String,
+    pub drivetrain: Drivetrain,
+    /// Distance between the left and right wheels, in inches
+    pub track_width: f32,
+    /// Wheel diameter, in inches
+    pub wheel_diameter: f32,
+    /// Wheel rotations per motor rotation
+    pub gear_ratio: f32,
+    /// Motor cartridge speed, in rotations per minute
+    pub max_rpm: f32,
+    /// Encoder ticks per motor rotation
+    pub ticks_per_rotation: f32,
+    /// Side to side size, in inches
+    pub width: f32,
+    /// Front to back size, in inches
+    pub length: f32,
+}
+
+impl Default for Robot {
+    fn default() -> Self {
+        Self {
+            name: "Default".into(),
+            drivetrain: Drivetrain::Tank,
+            track_width: 12.0,
+            wheel_diameter: 3.25,
+            gear_ratio: 0.75,
+            max_rpm: 600.0,
+            ticks_per_rotation: 300.0,
+            width: 15.0,
+            length: 15.0,
+        }
+    }
+}
+
+impl Robot {
+    /// Theoretical top speed, in inches per second.
+    pub fn max_velocity(&self) -> f32 {
+        let wheel =
+            self.max_rpm * self.gear_ratio / 60.0 * std::f32::consts::PI * self.wheel_diameter;
+        match self.drivetrain {
+            // Wheels at 45 degrees combine to push faster than any single one
+            Drivetrain::XDrive => wheel * std::f32::consts::SQRT_2,
+            Drivetrain::Tank | Drivetrain::Holonomic => wheel,
+        }
+    }
+
+    /// Motor rotations needed to drive a distance in inches.
+    pub fn motor_rotations(&self, inches: f32) -> f32 {
+        inches / (std::f32::consts::PI * self.wheel_diameter) / self.gear_ratio
+    }
+
+    /// Estimates how long a command takes, in seconds,
+    /// accelerating and decelerating at `acceleration` inches per second squared.
+    pub fn command_time(&self, command: &Command, acceleration: f32) -> f32 {
+        self.limited_time(command, acceleration, 100.0)
+    }
+
+    /// Estimates how long each of a sequence of commands takes, in seconds,
+    /// keeping to the speed limits set along the way.
+    pub fn command_times(&self, commands: &[Command], acceleration: f32) -> Vec<f32> {
+        let mut speed = 100.0;
+        commands
+            .iter()
+            .map(|command| {
+                if let Command::Speed(percent) = *command {
+                    speed = percent;
+                }
+                self.limited_time(command, acceleration, speed)
+            })
+            .collect()
+    }
+
+    /// Estimates how long a sequence of commands takes, in seconds.
+    pub fn path_time(&self, commands: &[Command], acceleration: f32) -> f32 {
+        self.command_times(commands, acceleration).iter().sum()
+    }
+
+    /// Time a command takes with the robot limited to a percentage of top speed.
+    fn limited_time(&self, command: &Command, acceleration: f32, speed: f32) -> f32 {
+        let distance = match *command {
+            Command::Drive(length) => length.abs(),
+            // Each side covers an arc of half the track width
+            Command::Turn(angle) => angle.to_radians().abs() * self.track_width / 2.0,
+            // The outer side covers the most distance
+            Command::Arc { radius, angle } => {
+                angle.to_radians().abs() * (radius + self.track_width / 2.0)
+            }
+            Command::Speed(_) | Command::Marker(_) => 0.0,
+        };
+        let velocity = self.max_velocity() * speed.clamp(0.0, 100.0) / 100.0;
+        profile_time(distance, velocity, acceleration)
+    }
+}
+
+/// Time to cover a distance from rest to rest with a trapezoidal velocity profile.
+fn profile_time(distance: f32, velocity: f32, acceleration: f32) -> f32 {
+    if distance <= 0.0 || velocity <= 0.0 || acceleration <= 0.0 {
+        return 0.0;
+    }
+    let ramp = velocity * velocity / acceleration;
+    if distance < ramp {
+        // Never reaches top speed
+        2.0 * f32::sqrt(distance / acceleration)
+    } else {
+        velocity / acceleration + distance / velocity
+    }
+}
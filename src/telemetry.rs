@@ -0,0 +1,235 @@
+use egui::{pos2, Pos2};
+use serde_json::Value;
+
+use crate::field::Frame;
+use crate::generate::waypoints;
+use crate::geometry::segments;
+use crate::point::Point;
+
+/// Column names accepted for each value in a log, lowercase.
+const TIME_NAMES: &[&str] = &["time", "t", "timestamp"];
+const X_NAMES: &[&str] = &["x"];
+const Y_NAMES: &[&str] = &["y"];
+const HEADING_NAMES: &[&str] = &["heading", "theta", "angle", "h"];
+
+/// A pose the robot logged during a run, in Pathy's coordinates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pose {
+    /// Seconds since the run started
+    pub time: f32,
+    pub pos: Pos2,
+    /// Degrees clockwise from +x, if it was logged
+    pub heading: Option<f32>,
+}
+
+/// A logged run, shown over the planned path.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Run {
+    pub name: String,
+    pub poses: Vec<Pose>,
+}
+
+impl Run {
+    /// Seconds from the first logged pose to the last.
+    pub fn duration(&self) -> f32 {
+        match (self.poses.first(), self.poses.last()) {
+            (Some(first), Some(last)) => last.time - first.time,
+            _ => 0.0,
+        }
+    }
+}
+
+/// How far a run strayed from the path between two waypoints.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Deviation {
+    /// Logged poses closest to this part of the path
+    pub samples: usize,
+    /// Average distance from the path, in inches
+    pub mean: f32,
+    /// Largest distance from the path, in inches
+    pub max: f32,
+}
+
+/// Reads an odometry log of time, x, y and heading, measured in `frame` like generated code.
+///
+/// CSV logs have a row per pose, with an optional header naming the columns;
+/// without one the columns are time, x, y and heading. JSON logs are an array of
+/// `{"time", "x", "y", "heading"}` objects or of `[time, x, y, heading]` arrays.
+/// Heading may be left out either way.
+pub fn parse_log(text: &str, frame: &Frame) -> Result<Vec<Pose>, String> {
+    let rows = if text.trim_start().starts_with('[') {
+        json_rows(text)?
+    } else {
+        csv_rows(text)?
+    };
+    if rows.is_empty() {
+        return Err("No poses found in log".into());
+    }
+    Ok(rows
+        .into_iter()
+        .map(|[time, x, y, heading]| Pose {
+            time: time.unwrap_or_default(),
+            pos: frame.from_frame(pos2(x.unwrap_or_default(), y.unwrap_or_default())),
+            heading: heading.map(|h| frame.heading(h)),
+        })
+        .collect())
+}
+
+/// Finds a named column in a header.
+fn column(header: &[String], names: &[&str]) -> Option<usize> {
+    header.iter().position(|h| names.contains(&h.as_str()))
+}
+
+/// Reads time, x, y and heading from each CSV row.
+fn csv_rows(text: &str) -> Result<Vec<[Option<f32>; 4]>, String> {
+    let mut columns = [Some(0), Some(1), Some(2), Some(3)];
+    let mut rows = Vec::new();
+    let lines = text
+        .lines()
+        .map(str::trim)
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+    for (i, line) in lines {
+        let cells: Vec<&str> = line.split(',').map(str::trim).collect();
+        if rows.is_empty() && cells.iter().any(|c| c.parse::<f32>().is_err()) {
+            let header: Vec<String> = cells.iter().map(|c| c.to_lowercase()).collect();
+            columns = [TIME_NAMES, X_NAMES, Y_NAMES, HEADING_NAMES].map(|n| column(&header, n));
+            if columns[1].is_none() || columns[2].is_none() {
+                return Err("Log header needs x and y columns".into());
+            }
+            continue;
+        }
+        let mut row = [None; 4];
+        for (value, column) in row.iter_mut().zip(columns) {
+            let Some(cell) = column.and_then(|c| cells.get(c)) else {
+                continue;
+            };
+            *value = Some(
+                cell.parse()
+                    .map_err(|_| format!("Line {}: invalid number `{cell}`", i + 1))?,
+            );
+        }
+        if row[1].is_none() || row[2].is_none() {
+            return Err(format!("Line {}: missing x or y", i + 1));
+        }
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+/// Reads time, x, y and heading from each JSON entry.
+fn json_rows(text: &str) -> Result<Vec<[Option<f32>; 4]>, String> {
+    let value: Value = serde_json::from_str(text).map_err(|e| format!("Invalid log: {e}"))?;
+    let entries = value.as_array().ok_or("Log should be an array of poses")?;
+    entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            let number = |value: Option<&Value>| value.and_then(Value::as_f64).map(|v| v as f32);
+            let row = match entry {
+                Value::Array(values) => [0, 1, 2, 3].map(|c| number(values.get(c))),
+                Value::Object(map) => [TIME_NAMES, X_NAMES, Y_NAMES, HEADING_NAMES]
+                    .map(|names| number(names.iter().find_map(|n| map.get(*n)))),
+                _ => [None; 4],
+            };
+            if row[1].is_none() || row[2].is_none() {
+                return Err(format!("Pose {}: missing x or y", i + 1));
+            }
+            Ok(row)
+        })
+        .collect()
+}
+
+/// Compares a run against the planned path, for each pair of neighbouring waypoints.
+///
+/// Poses count toward the part of the path they're closest to, never going back
+/// to an earlier part, so paths that cross themselves are compared in order.
+pub fn deviations(points: &[Point], poses: &[Pose]) -> Vec<Deviation> {
+    let segments = segments(&waypoints(points));
+    let mut result = vec![Deviation::default(); points.len().saturating_sub(1)];
+    let mut first = 0;
+    for pose in poses {
+        let closest = segments[first..]
+            .iter()
+            .enumerate()
+            .map(|(i, (pair, segment))| (first + i, *pair, segment.distance(pose.pos)))
+            .min_by(|a, b| a.2.total_cmp(&b.2));
+        let Some((index, pair, distance)) = closest else {
+            break;
+        };
+        first = index;
+        let deviation = &mut result[pair];
+        deviation.samples += 1;
+        deviation.mean += distance;
+        deviation.max = deviation.max.max(distance);
+    }
+    for deviation in &mut result {
+        if deviation.samples > 0 {
+            deviation.mean /= deviation.samples as f32;
+        }
+    }
+    result
+}
+
+/// Distance from where the run ended to the last waypoint, in inches.
+pub fn end_error(points: &[Point], poses: &[Pose]) -> Option<f32> {
+    let (point, pose) = (points.last()?, poses.last()?);
+    Some(pose.pos.distance(pos2(point.x, point.y)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::field::Origin;
+
+    #[test]
+    fn reads_csv_and_json_logs() {
+        let frame = Frame {
+            origin: Origin::Center,
+            ..Default::default()
+        };
+        let csv = "# odometry\nt, heading, x, y\n0, 90, 0, 0\n0.5, 45, 10, -10\n";
+        let poses = parse_log(csv, &frame).unwrap();
+        assert_eq!(poses.len(), 2);
+        assert_eq!(poses[1].pos, pos2(80.25, 80.25));
+        assert_eq!(poses[1].heading, Some(-45.0));
+
+        let json = r#"[{"time": 0, "x": 0, "y": 0}, [0.5, 10, -10, 45]]"#;
+        let from_json = parse_log(json, &frame).unwrap();
+        assert_eq!(from_json[0].heading, None);
+        assert_eq!(from_json[1], poses[1]);
+
+        assert!(parse_log("time, heading\n0, 0", &frame).is_err());
+        assert!(parse_log("0, 1, two, 3", &frame).is_err());
+        assert!(parse_log("", &frame).is_err());
+    }
+
+    #[test]
+    fn deviation_is_measured_per_waypoint_pair() {
+        let points = [
+            Point::new(0.0, 0.0),
+            Point::new(48.0, 0.0),
+            Point::new(48.0, 48.0),
+        ];
+        let pose = |x, y| Pose {
+            time: 0.0,
+            pos: pos2(x, y),
+            heading: None,
+        };
+        // Drifting 2 inches off the first line, then right on the second
+        let poses = [
+            pose(0.0, 0.0),
+            pose(24.0, 2.0),
+            pose(47.0, 1.0),
+            pose(48.0, 24.0),
+            pose(48.0, 47.0),
+        ];
+        let result = deviations(&points, &poses);
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].samples, 3);
+        assert_eq!(result[0].max, 2.0);
+        assert!((result[0].mean - 1.0).abs() < 1e-5);
+        assert_eq!((result[1].samples, result[1].max), (2, 0.0));
+        assert_eq!(end_error(&points, &poses), Some(1.0));
+    }
+}
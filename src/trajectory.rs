@@ -0,0 +1,312 @@
+use egui::Pos2;
+
+use crate::geometry;
+use crate::robot::Robot;
+
+/// Settings used to time a path for trajectory following.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct TrajectoryOptions {
+    /// Acceleration available to the robot, in inches per second squared
+    pub max_acceleration: f32,
+    /// Time between states, in seconds
+    pub time_step: f32,
+}
+
+impl Default for TrajectoryOptions {
+    fn default() -> Self {
+        Self {
+            max_acceleration: 80.0,
+            time_step: 0.01,
+        }
+    }
+}
+
+/// The state of the robot at a moment along a trajectory.
+///
+/// Headings and angular velocities are positive clockwise, matching generated turns.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct State {
+    /// Time since the start, in seconds
+    pub time: f32,
+    pub pos: Pos2,
+    /// Heading, in degrees
+    pub heading: f32,
+    /// Linear velocity, in inches per second
+    pub velocity: f32,
+    /// Angular velocity, in radians per second
+    pub angular_velocity: f32,
+    /// Left wheel velocity, in inches per second
+    pub left: f32,
+    /// Right wheel velocity, in inches per second
+    pub right: f32,
+}
+
+/// Distance between samples used to build the velocity profile, in inches
+const SPACING: f32 = 0.5;
+
+/// Time between the states of a turn in place, in seconds
+const TURN_STEP: f32 = 0.01;
+
+/// Generates a time-parameterized trajectory along a path.
+///
+/// The robot starts and ends at rest, never exceeds the acceleration limit and slows down
+/// through curves so that the outer wheel stays below the velocity limit.
+/// Sharp corners can't be driven through, so the robot stops at them and turns in place.
+pub fn generate(path: &[(Pos2, f32)], robot: &Robot, options: &TrajectoryOptions) -> Vec<State> {
+    let mut states: Vec<State> = Vec::new();
+    let mut start = 0;
+    for corner in 1..path.len() {
+        if corner < path.len() - 1 && !is_sharp(path, corner) {
+            continue;
+        }
+        let offset = states.last().map_or(0.0, |s| s.time);
+        let leg = drive(&path[start..=corner], robot, options);
+        // The leg starts where the robot came to rest, so its first state is already there
+        let skip = usize::from(!states.is_empty());
+        states.extend(leg.into_iter().skip(skip).map(|state| State {
+            time: state.time + offset,
+            ..state
+        }));
+        if corner < path.len() - 1 {
+            let incoming = path[corner].0 - path[corner - 1].0;
+            let outgoing = path[corner + 1].0 - path[corner].0;
+            let turn = geometry::wrap_angle(outgoing.angle() - incoming.angle());
+            if let Some(&last) = states.last() {
+                states.extend(turn_in_place(last, turn, robot, options));
+            }
+        }
+        start = corner;
+    }
+    resample(&states, options.time_step)
+}
+
+/// Whether the robot has to stop and turn in place at a waypoint.
+fn is_sharp(path: &[(Pos2, f32)], i: usize) -> bool {
+    let incoming = path[i].0 - path[i - 1].0;
+    let outgoing = path[i + 1].0 - path[i].0;
+    path[i].1 <= 0.0
+        && incoming.length() > f32::EPSILON
+        && outgoing.length() > f32::EPSILON
+        && geometry::wrap_angle(outgoing.angle() - incoming.angle()).abs()
+            > geometry::TURN_THRESHOLD
+}
+
+/// States turning in place by `turn` radians from rest at `from`,
+/// with each wheel keeping to the velocity and acceleration limits.
+fn turn_in_place(from: State, turn: f32, robot: &Robot, options: &TrajectoryOptions) -> Vec<State> {
+    let half_track = robot.track_width / 2.0;
+    let accel = options.max_acceleration.max(f32::EPSILON);
+    let top = robot.max_velocity().max(f32::EPSILON);
+    // Distance each wheel covers, with a trapezoidal (or triangular) wheel velocity profile
+    let distance = turn.abs() * half_track;
+    let peak = top.min(f32::sqrt(distance * accel));
+    let ramp = peak / accel;
+    let total = 2.0 * ramp + (distance - peak * ramp) / peak.max(f32::EPSILON);
+    let steps = (total / TURN_STEP).ceil().max(1.0) as usize;
+    (1..=steps)
+        .map(|i| {
+            let t = total * i as f32 / steps as f32;
+            let (covered, speed) = if t < ramp {
+                (accel * t * t / 2.0, accel * t)
+            } else if t > total - ramp {
+                let left = total - t;
+                (distance - accel * left * left / 2.0, accel * left)
+            } else {
+                (peak * ramp / 2.0 + peak * (t - ramp), peak)
+            };
+            let angular_velocity = speed / half_track * turn.signum();
+            let heading = from.heading.to_radians() + covered / half_track * turn.signum();
+            State {
+                time: from.time + t,
+                pos: from.pos,
+                heading: geometry::wrap_angle(heading).to_degrees(),
+                velocity: 0.0,
+                angular_velocity,
+                left: angular_velocity * half_track,
+                right: -angular_velocity * half_track,
+            }
+        })
+        .collect()
+}
+
+/// Times driving along a path from rest to rest, one state per sample.
+fn drive(path: &[(Pos2, f32)], robot: &Robot, options: &TrajectoryOptions) -> Vec<State> {
+    let track_width = robot.track_width;
+    let samples = geometry::sample(path, SPACING);
+    if samples.len() < 2 {
+        return Vec::new();
+    }
+    let accel = options.max_acceleration.max(f32::EPSILON);
+
+    // Curvature limits, then forward and backward acceleration passes
+    let mut velocity: Vec<f32> = samples
+        .iter()
+        .map(|s| robot.max_velocity() / (1.0 + s.curvature.abs() * track_width / 2.0))
+        .collect();
+    velocity[0] = 0.0;
+    *velocity.last_mut().unwrap() = 0.0;
+    for i in 1..samples.len() {
+        let gap = samples[i].distance - samples[i - 1].distance;
+        velocity[i] = velocity[i].min(f32::sqrt(velocity[i - 1].powi(2) + 2.0 * accel * gap));
+    }
+    for i in (0..samples.len() - 1).rev() {
+        let gap = samples[i + 1].distance - samples[i].distance;
+        velocity[i] = velocity[i].min(f32::sqrt(velocity[i + 1].powi(2) + 2.0 * accel * gap));
+    }
+
+    // Time each sample
+    let mut states: Vec<State> = Vec::with_capacity(samples.len());
+    for (i, sample) in samples.iter().enumerate() {
+        let time = match states.last() {
+            Some(prev) => {
+                let gap = sample.distance - samples[i - 1].distance;
+                let average = (prev.velocity + velocity[i]) / 2.0;
+                prev.time
+                    + if average > f32::EPSILON {
+                        gap / average
+                    } else {
+                        0.0
+                    }
+            }
+            None => 0.0,
+        };
+        // Heading follows the direction of travel through the sample
+        let from = samples[i.saturating_sub(1)].pos;
+        let to = samples[(i + 1).min(samples.len() - 1)].pos;
+        let angular_velocity = velocity[i] * sample.curvature;
+        states.push(State {
+            time,
+            pos: sample.pos,
+            heading: (to - from).angle().to_degrees(),
+            velocity: velocity[i],
+            angular_velocity,
+            left: velocity[i] + angular_velocity * track_width / 2.0,
+            right: velocity[i] - angular_velocity * track_width / 2.0,
+        });
+    }
+    states
+}
+
+/// Interpolates states at a fixed time step.
+fn resample(states: &[State], time_step: f32) -> Vec<State> {
+    let time_step = time_step.max(0.001);
+    let end = states.last().map_or(0.0, |s| s.time);
+    let mut result = Vec::new();
+    let mut idx = 0;
+    let mut time = 0.0;
+    while time < end {
+        while states[idx + 1].time < time {
+            idx += 1;
+        }
+        let (a, b) = (states[idx], states[idx + 1]);
+        let t = if b.time > a.time {
+            (time - a.time) / (b.time - a.time)
+        } else {
+            0.0
+        };
+        let lerp = |x: f32, y: f32| x + (y - x) * t;
+        result.push(State {
+            time,
+            pos: a.pos.lerp(b.pos, t),
+            heading: geometry::wrap_angle(
+                a.heading.to_radians()
+                    + geometry::wrap_angle((b.heading - a.heading).to_radians()) * t,
+            )
+            .to_degrees(),
+            velocity: lerp(a.velocity, b.velocity),
+            angular_velocity: lerp(a.angular_velocity, b.angular_velocity),
+            left: lerp(a.left, b.left),
+            right: lerp(a.right, b.right),
+        });
+        time = result.len() as f32 * time_step;
+    }
+    if let Some(last) = states.last() {
+        result.push(*last);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::pos2;
+
+    #[test]
+    fn respects_robot_limits() {
+        let robot = Robot::default();
+        let options = TrajectoryOptions::default();
+        let path = [
+            (pos2(0.0, 0.0), 0.0),
+            (pos2(48.0, 0.0), 12.0),
+            (pos2(48.0, 48.0), 0.0),
+        ];
+        let states = generate(&path, &robot, &options);
+        let (first, last) = (states.first().unwrap(), states.last().unwrap());
+        assert_eq!(first.velocity, 0.0);
+        assert_eq!(last.velocity, 0.0);
+        assert_eq!(last.pos, pos2(48.0, 48.0));
+        for pair in states.windows(2) {
+            assert!(pair[1].time > pair[0].time);
+            let accel = (pair[1].velocity - pair[0].velocity) / (pair[1].time - pair[0].time);
+            assert!(
+                accel.abs() <= options.max_acceleration * 1.05,
+                "accelerated at {accel}"
+            );
+        }
+        for state in &states {
+            let fastest = state.left.abs().max(state.right.abs());
+            assert!(
+                fastest <= robot.max_velocity() * 1.01,
+                "wheel ran at {fastest}"
+            );
+        }
+    }
+
+    #[test]
+    fn stops_to_turn_at_sharp_corners() {
+        let robot = Robot::default();
+        let options = TrajectoryOptions::default();
+        let path = [
+            (pos2(0.0, 0.0), 0.0),
+            (pos2(48.0, 0.0), 0.0),
+            (pos2(48.0, 48.0), 0.0),
+        ];
+        let states = generate(&path, &robot, &options);
+        let corner: Vec<&State> = states
+            .iter()
+            .filter(|s| s.pos.distance(pos2(48.0, 0.0)) < 1e-3)
+            .collect();
+        // Still at the corner, the robot turns from facing +x to facing +y
+        assert!(corner.iter().all(|s| s.velocity == 0.0));
+        assert!(corner.iter().any(|s| (s.heading - 45.0).abs() < 5.0));
+        assert!(corner.iter().any(|s| s.angular_velocity > 0.0));
+        for state in &states {
+            assert!((-180.0..180.0).contains(&state.heading));
+            let fastest = state.left.abs().max(state.right.abs());
+            assert!(fastest <= robot.max_velocity() * 1.01);
+        }
+        for pair in states.windows(2) {
+            assert!(pair[1].time > pair[0].time);
+            // Headings only change gradually, even at the corner
+            let step = geometry::wrap_angle((pair[1].heading - pair[0].heading).to_radians());
+            assert!(step.abs() < 0.5, "heading jumped by {step} radians");
+        }
+        assert!((states.last().unwrap().heading - 90.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn interpolated_headings_stay_wrapped() {
+        let state = |time: f32, heading: f32| State {
+            time,
+            pos: pos2(0.0, 0.0),
+            heading,
+            velocity: 0.0,
+            angular_velocity: 0.0,
+            left: 0.0,
+            right: 0.0,
+        };
+        let states = resample(&[state(0.0, 170.0), state(1.0, -170.0)], 0.75);
+        assert!((states[1].heading - -175.0).abs() < 1e-3, "{states:?}");
+    }
+}
@@ -0,0 +1,218 @@
+use pathy::generate::{ArcStyle, BranchStyle, DriveUnit};
+use pathy::path::Branch;
+use pathy::{Backend, Point, Project};
+
+/// Builds the path used for every snapshot: a straight start, a filleted corner,
+/// a sharp corner and a turn back past 180 degrees.
+fn project() -> Project {
+    let mut corner = Point::new(48.0, 24.0);
+    corner.radius = 8.0;
+    let mut project = Project::new(vec![
+        Point::new(24.0, 24.0),
+        corner,
+        Point::new(48.0, 72.0),
+        Point::new(20.0, 70.0),
+    ]);
+    // Keep sampled outputs short
+    project.options.pursuit.spacing = 6.0;
+    project.options.trajectory.time_step = 0.25;
+    project
+}
+
+/// Compares generated output against `tests/golden/<name>.txt`.
+/// Run with `UPDATE_GOLDEN=1` to write new snapshots after an intended change.
+fn check(name: &str, output: &str) {
+    let path = format!("{}/tests/golden/{name}.txt", env!("CARGO_MANIFEST_DIR"));
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(&path, output).unwrap();
+        return;
+    }
+    let expected = std::fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("missing snapshot {path}, run with UPDATE_GOLDEN=1: {e}"));
+    assert_eq!(
+        output, expected,
+        "{name} output changed, run with UPDATE_GOLDEN=1 if this is intended"
+    );
+}
+
+#[test]
+fn chassis() {
+    check("chassis", &project().generate());
+}
+
+#[test]
+fn chassis_wheels_in_ticks() {
+    let mut project = project();
+    project.options.arc_style = ArcStyle::Wheels;
+    project.options.drive_unit = DriveUnit::Ticks;
+    check("chassis_wheels_ticks", &project.generate());
+}
+
+#[test]
+fn pursuit_array() {
+    let mut project = project();
+    project.options.backend = Backend::PursuitArray;
+    check("pursuit_array", &project.generate());
+}
+
+#[test]
+fn pursuit_file() {
+    let mut project = project();
+    project.options.backend = Backend::PursuitFile;
+    check("pursuit_file", &project.generate());
+}
+
+#[test]
+fn trajectory_csv() {
+    let mut project = project();
+    project.options.backend = Backend::TrajectoryCsv;
+    check("trajectory_csv", &project.generate());
+}
+
+#[test]
+fn trajectory_header() {
+    let mut project = project();
+    project.options.backend = Backend::TrajectoryHeader;
+    check("trajectory_header", &project.generate());
+}
+
+/// Adds a detour that rejoins at the last point, and a branch that ends the run early.
+fn branched_project() -> Project {
+    let mut project = project();
+    let points = project.path.points().to_vec();
+    project.path.branches_mut().extend([
+        Branch {
+            name: "grab goal".into(),
+            condition: "goal_sensor.detected()".into(),
+            from: points[1].id,
+            rejoin: Some(points[3].id),
+            points: vec![Point::new(72.0, 48.0)],
+        },
+        Branch {
+            name: "park".into(),
+            condition: "timer.elapsed() > 40".into(),
+            from: points[3].id,
+            rejoin: None,
+            points: vec![Point::new(20.0, 100.0)],
+        },
+    ]);
+    project
+}
+
+#[test]
+fn chassis_branches() {
+    check("chassis_branches", &branched_project().generate());
+}
+
+#[test]
+fn chassis_branch_functions() {
+    let mut project = branched_project();
+    project.options.branch_style = BranchStyle::Functions;
+    check("chassis_branch_functions", &project.generate());
+}
+
+#[test]
+fn branch_functions_get_unique_names() {
+    let mut project = branched_project();
+    project.options.branch_style = BranchStyle::Functions;
+    project.path.branches_mut()[1].name = "grab-goal".into();
+    let code = project.generate();
+    assert!(code.contains("void grab_goal() {"), "generated {code}");
+    assert!(code.contains("void grab_goal_2() {"), "generated {code}");
+}
+
+#[test]
+fn branches_from_the_start_face_the_main_path() {
+    let mut project = project();
+    let start = project.path.points()[0].id;
+    project.path.branches_mut().push(Branch {
+        name: "down".into(),
+        condition: "flag".into(),
+        from: start,
+        rejoin: None,
+        points: vec![Point::new(24.0, 48.0)],
+    });
+    let code = project.generate();
+    let branch: Vec<&str> = code
+        .lines()
+        .skip_while(|line| !line.starts_with("if (flag)"))
+        .skip(1)
+        .take(2)
+        .collect();
+    // The main path starts towards +x, so the branch turns to drive towards +y
+    assert_eq!(
+        branch,
+        ["    chassis.turn(90);", "    chassis.drive(24);"],
+        "generated {code}"
+    );
+}
+
+#[test]
+fn start_markers_run_once_with_branches() {
+    let mut project = branched_project();
+    let start = project.path.points()[0].id;
+    project.path.get_mut(start).unwrap().markers = vec!["intake.spin();".into()];
+    let code = project.generate();
+    assert_eq!(
+        code.matches("intake.spin();").count(),
+        1,
+        "generated {code}"
+    );
+    assert!(code.find("intake.spin();") < code.find("chassis.drive"));
+}
+
+#[test]
+fn too_few_points() {
+    let project = Project::new(vec![Point::new(0.0, 0.0)]);
+    assert_eq!(project.generate(), "// Create two points to get started");
+}
+
+#[test]
+fn mirrored_paths_turn_the_other_way() {
+    let mut mirrored = project();
+    // Any width works, mirrored paths only change direction
+    let width = 144.0;
+    mirrored.path.mirror(width);
+    let turns = |project: &Project| -> Vec<String> {
+        project
+            .generate()
+            .lines()
+            .filter(|line| line.contains("turn") || line.contains("arc"))
+            .map(String::from)
+            .collect()
+    };
+    assert_eq!(
+        turns(&mirrored),
+        ["chassis.arc(8, -90);", "chassis.turn(-94.09);"]
+    );
+    mirrored.path.mirror(width);
+    assert_eq!(mirrored.generate(), project().generate());
+}
+
+#[test]
+fn project_round_trips_through_json() {
+    let project = project();
+    let loaded = Project::from_json(&project.to_json()).unwrap();
+    assert_eq!(loaded.path, project.path);
+    assert_eq!(loaded.generate(), project.generate());
+
+    let project = branched_project();
+    let loaded = Project::from_json(&project.to_json()).unwrap();
+    assert_eq!(loaded.path, project.path);
+}
+
+#[test]
+fn loads_legacy_point_lists() {
+    let json = r#"[
+        {"x": 0, "y": 0, "selected": false, "locked": false, "animated": true,
+         "id": "00000000-0000-0000-0000-000000000001"},
+        {"x": 24, "y": 0, "selected": false, "locked": false, "animated": true,
+         "id": "00000000-0000-0000-0000-000000000002"}
+    ]"#;
+    let project = Project::from_json(json).unwrap();
+    assert_eq!(project.path.len(), 2);
+    assert_eq!(
+        project.generate(),
+        "// Generated by Pathy\n\nchassis.drive(24);\n"
+    );
+}